@@ -9,24 +9,35 @@ const ROUNDS_NUMBER: usize = 11;
 pub struct AES128 {
     keys: Vec<[[u8; BYTES_PER_ROW]; BYTES_PER_ROW]>,
     iv: Option<[u8;BLOCK_SIZE]>,
+    // Second chaining block, only used by IGE
+    ige_iv: Option<[u8;BLOCK_SIZE]>,
 }
 
 impl AES128 {
     pub fn new_str_key(key: &str, iv: Option<[u8;BLOCK_SIZE]>) -> Result<AES128, AESError> {
         // Key size is fixed to 16 for AES-128
-        if key.as_bytes().len() != KEY_SIZE_BYTES {
+        if key.len() != KEY_SIZE_BYTES {
             dbg!(format!("Key size should be {} bytes!", KEY_SIZE_BYTES));
-            return Err(AESError::WrongKeySize(key.as_bytes().len(), KEY_SIZE_BYTES));
+            return Err(AESError::WrongKeySize(key.len(), KEY_SIZE_BYTES));
         }
         let key: [u8;KEY_SIZE_BYTES] = key.as_bytes().try_into().unwrap();
 
         let keys = Self::key_schedule(&key)?;
         Ok(AES128 {
             keys,
-            iv
+            iv,
+            ige_iv: None
         })
     }
 
+    // IGE needs two blocks: the first one is the previous ciphertext, the second one the previous plaintext
+    pub fn new_ige(key: &[u8], iv: &[u8; 2*BLOCK_SIZE]) -> Result<Box<AES128>, AESError> {
+        let mut aes = Self::new(key, Some(iv[..BLOCK_SIZE].try_into().unwrap()))?;
+        aes.ige_iv = Some(iv[BLOCK_SIZE..].try_into().unwrap());
+
+        Ok(aes)
+    }
+
     pub fn encrypt_file(&self, filename: &str) -> Result<(), Error> {
         let data = read_from_file(filename)?;
         println!("{:?}", &data[..BLOCK_SIZE]);
@@ -76,7 +87,7 @@ impl KeySchedule for AES128 {
         // Leave first key untouched
         for i in 1..ROUNDS_NUMBER {
             let last_key = generated_keys[i-1];
-            let mut new_key = last_key.clone();
+            let mut new_key = last_key;
             let last_column = &mut last_key[3].clone();
             
             Self::rot_word(last_column);
@@ -84,10 +95,10 @@ impl KeySchedule for AES128 {
             Self::r_con(last_column, i);
             
             // New first column
-            Self::add_to_column(&mut new_key[0], &last_column);
+            Self::add_to_column(&mut new_key[0], last_column);
             // New second, third and fourth columns
             for i in 1..BYTES_PER_ROW {
-                let new_column = new_key[i-1].clone();
+                let new_column = new_key[i-1];
                 Self::add_to_column(&mut new_key[i], &new_column);
             }
 
@@ -96,8 +107,8 @@ impl KeySchedule for AES128 {
         }
 
         // Return keys to normal (by-row) view
-        for i in 0..generated_keys.len() {
-            transpose(&mut generated_keys[i]);
+        for key in generated_keys.iter_mut() {
+            transpose(key);
         }
 
         Ok(generated_keys.to_vec())
@@ -121,7 +132,8 @@ impl AES for AES128 {
         Ok(Box::new(
             Self {
                 keys,
-                iv
+                iv,
+                ige_iv: None
         }))
     }
 
@@ -131,7 +143,7 @@ impl AES for AES128 {
         let keys = &self.keys;
 
         // First round
-        Self::add_round_key(&mut block, &keys, 0);
+        Self::add_round_key(&mut block, keys, 0);
 
         // For 9 rounds
         for i in 1..ROUNDS_NUMBER-1 {
@@ -146,8 +158,7 @@ impl AES for AES128 {
         Self::shift_rows(&mut block);
         Self::add_round_key(&mut block, keys, 10);
 
-        let result = matrix_to_array(&block);
-        result
+        matrix_to_array(&block)
     }
 
     fn encrypt_blocks(&self, data: &[[u8; BLOCK_SIZE]], mode: AESMode) 
@@ -184,6 +195,35 @@ impl AES for AES128 {
                     tmp.push(add_iv(block, &iv));
                 }
 
+                tmp
+            },
+            AESMode::PCBC => {
+                // Chain both the previous plaintext and ciphertext
+                let mut tmp = Vec::new();
+                let mut iv = self.iv
+                        .ok_or(AESError::ModeRequiresIV(mode))?;
+
+                for block in data {
+                    let encrypted = self.encrypt_block(&add_iv(block, &iv));
+                    iv = add_iv(block, &encrypted);
+                    tmp.push(encrypted);
+                }
+
+                tmp
+            },
+            AESMode::IGE => {
+                let mut tmp = Vec::new();
+                let mut last_ciphertext = self.iv
+                        .ok_or(AESError::ModeRequiresIV(mode))?;
+                let mut last_plaintext = self.ige_iv
+                        .ok_or(AESError::ModeRequiresIV(mode))?;
+
+                for block in data {
+                    last_ciphertext = add_iv(&self.encrypt_block(&add_iv(block, &last_ciphertext)), &last_plaintext);
+                    last_plaintext = *block;
+                    tmp.push(last_ciphertext);
+                }
+
                 tmp
             }
         };
@@ -223,8 +263,7 @@ impl AES for AES128 {
         Self::substitute_bytes(&mut block, true);
         Self::add_round_key(&mut block, keys, 0);
 
-        let result = matrix_to_array(&block);
-        result
+        matrix_to_array(&block)
     }
 
     fn decrypt_blocks(&self, data: &[[u8; BLOCK_SIZE]], mode: AESMode)
@@ -242,7 +281,7 @@ impl AES for AES128 {
                 for i in (1..data.len()).rev() {
                     tmp.push(add_iv(&self.decrypt_block(&data[i]), &data[i-1]));
                 }
-                if data.len() > 0 {
+                if !data.is_empty() {
                     let iv = self.iv.as_ref().ok_or(AESError::ModeRequiresIV(mode))?;
                     tmp.push(add_iv(&self.decrypt_block(&data[0]), iv));
                 }
@@ -252,6 +291,34 @@ impl AES for AES128 {
             AESMode::OFB => {
                 // Same as encrypt
                 self.encrypt_blocks(data, mode)?
+            },
+            AESMode::PCBC => {
+                let mut tmp = Vec::new();
+                let mut iv = self.iv
+                        .ok_or(AESError::ModeRequiresIV(mode))?;
+
+                for block in data {
+                    let decrypted = add_iv(&self.decrypt_block(block), &iv);
+                    iv = add_iv(block, &decrypted);
+                    tmp.push(decrypted);
+                }
+
+                tmp
+            },
+            AESMode::IGE => {
+                let mut tmp = Vec::new();
+                let mut last_ciphertext = self.iv
+                        .ok_or(AESError::ModeRequiresIV(mode))?;
+                let mut last_plaintext = self.ige_iv
+                        .ok_or(AESError::ModeRequiresIV(mode))?;
+
+                for block in data {
+                    last_plaintext = add_iv(&self.decrypt_block(&add_iv(block, &last_plaintext)), &last_ciphertext);
+                    last_ciphertext = *block;
+                    tmp.push(last_plaintext);
+                }
+
+                tmp
             }
         };
 
@@ -275,8 +342,10 @@ impl AES for AES128 {
 }
 
 #[cfg(test)]
+// Tests from before the lints were enforced, kept as written
+#[allow(clippy::clone_on_copy)]
 mod aes128_tests {
-    use crate::{aes128::AES128, key_schedule::KeySchedule, AESMode, AES};
+    use crate::{aes128::AES128, key_schedule::KeySchedule, utils::{add_iv, decode, split_in_blocks, unite_blocks}, AESError, AESMode, AES};
 
 
     #[test]
@@ -388,6 +457,76 @@ mod aes128_tests {
         assert_eq!(s, decrypted);
     }
 
+    #[test]
+    fn test_encrypt_decrypt_blocks_pcbc() {
+        let key: [u8;16] = [0xc3, 0x2c, 0x5c, 166, 181, 128, 94, 12, 219, 141, 165, 122, 42, 182, 254, 92];
+        let iv: [u8;16] = [0xee;16];
+        let aes = AES128::new(&key, Some(iv)).unwrap();
+        let data = [[0x01;16], [0x02;16], [0x03;16]];
+
+        let mode = AESMode::PCBC;
+        let crypted = aes.encrypt_blocks(&data, mode).unwrap();
+
+        // C_i = E(P_i ^ P_{i-1} ^ C_{i-1})
+        let first = aes.encrypt_block(&add_iv(&data[0], &iv));
+        let second = aes.encrypt_block(&add_iv(&data[1], &add_iv(&data[0], &first)));
+        assert_eq!(crypted[0], first);
+        assert_eq!(crypted[1], second);
+
+        let decrypted = aes.decrypt_blocks(&crypted, mode).unwrap();
+        assert_eq!(data.to_vec(), decrypted);
+    }
+
+    #[test]
+    fn test_pcbc_propagates_errors() {
+        let key: [u8;16] = [0xc3, 0x2c, 0x5c, 166, 181, 128, 94, 12, 219, 141, 165, 122, 42, 182, 254, 92];
+        let aes = AES128::new(&key, Some([0xee;16])).unwrap();
+        let data = [[0x01;16], [0x02;16], [0x03;16]];
+
+        let mut crypted = aes.encrypt_blocks(&data, AESMode::PCBC).unwrap();
+        crypted[0][0] ^= 1;
+        let decrypted = aes.decrypt_blocks(&crypted, AESMode::PCBC).unwrap();
+
+        // Unlike CBC, a corrupted block garbles every following block
+        assert_ne!(decrypted[1], data[1]);
+        assert_ne!(decrypted[2], data[2]);
+    }
+
+    #[test]
+    fn test_encrypt_blocks_ige() {
+        // IGE test vectors from OpenSSL
+        let key = decode("000102030405060708090a0b0c0d0e0f").unwrap();
+        let iv: [u8;32] = decode("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f").unwrap()
+                            .try_into().unwrap();
+        let aes = AES128::new_ige(&key, &iv).unwrap();
+        let expected = decode("1a8519a6557be652e9da8e43da4ef4453cf456b4ca488aa383c79c98b34797cb").unwrap();
+
+        let crypted = aes.encrypt_blocks(&[[0;16], [0;16]], AESMode::IGE).unwrap();
+        assert_eq!(unite_blocks(&crypted), expected);
+        let decrypted = aes.decrypt_blocks(&crypted, AESMode::IGE).unwrap();
+        assert_eq!(decrypted, vec![[0;16], [0;16]]);
+
+        let key = decode("5468697320697320616e20696d706c65").unwrap();
+        let iv: [u8;32] = decode("6d656e746174696f6e206f6620494745206d6f646520666f72204f70656e5353").unwrap()
+                            .try_into().unwrap();
+        let aes = AES128::new_ige(&key, &iv).unwrap();
+        let data = split_in_blocks(&decode("99706487a1cde613bc6de0b6f24b1c7aa448c8b9c3403e3467a8cad89340f53b").unwrap()).unwrap();
+        let expected = decode("4c2e204c6574277320686f70652042656e20676f74206974207269676874210a").unwrap();
+
+        let crypted = aes.encrypt_blocks(&data, AESMode::IGE).unwrap();
+        assert_eq!(unite_blocks(&crypted), expected);
+        assert_eq!(aes.decrypt_blocks(&crypted, AESMode::IGE).unwrap(), data);
+    }
+
+    #[test]
+    fn test_ige_requires_two_ivs() {
+        let key: [u8;16] = [0xc3, 0x2c, 0x5c, 166, 181, 128, 94, 12, 219, 141, 165, 122, 42, 182, 254, 92];
+        let aes = AES128::new(&key, Some([0xee;16])).unwrap();
+
+        assert!(matches!(aes.encrypt_blocks(&[[0;16]], AESMode::IGE),
+                        Err(AESError::ModeRequiresIV(AESMode::IGE))));
+    }
+
     // Only local test
     // #[test]
     fn test_image() {
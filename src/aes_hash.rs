@@ -0,0 +1,299 @@
+use crate::{aes128::AES128, key_schedule::KeySchedule, round_operations::Round,
+    utils::{add_iv, array_to_matrix, matrix_to_array}, AESError, AES, BLOCK_SIZE, BYTES_PER_ROW};
+
+// Bytes used by the message length in the last block (bit length, big-endian)
+const LENGTH_SIZE: usize = 8;
+// Initial value, with a zero IV the first blocks of MMO and Miyaguchi-Preneel would coincide
+const HASH_IV: [u8; BLOCK_SIZE] = [0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef, 0xfe, 0xdc, 0xba, 0x98, 0x76, 0x54, 0x32, 0x10];
+// Constant c of the Hirose construction, any non-zero value works
+const HIROSE_CONSTANT: [u8; BLOCK_SIZE] = [0xff; BLOCK_SIZE];
+// Hirose needs a key of two blocks: AES-256, 15 round keys
+const AES256_KEY_SIZE: usize = 2*BLOCK_SIZE;
+const AES256_ROUNDS: usize = 15;
+
+
+// Single-block-length compression functions f(H, m) built from AES-128
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HashConstruction {
+    // H' = E_m(H) ^ H
+    DaviesMeyer,
+    // H' = E_H(m) ^ m
+    MatyasMeyerOseas,
+    // H' = E_H(m) ^ m ^ H
+    MiyaguchiPreneel,
+}
+
+fn encrypt(key: &[u8; BLOCK_SIZE], block: &[u8; BLOCK_SIZE]) -> [u8; BLOCK_SIZE] {
+    AES128::new(key, None).unwrap().encrypt_block(block)
+}
+
+pub fn compress(construction: HashConstruction, h: &[u8; BLOCK_SIZE], m: &[u8; BLOCK_SIZE]) -> [u8; BLOCK_SIZE] {
+    match construction {
+        HashConstruction::DaviesMeyer => add_iv(&encrypt(m, h), h),
+        HashConstruction::MatyasMeyerOseas => add_iv(&encrypt(h, m), m),
+        HashConstruction::MiyaguchiPreneel => add_iv(&add_iv(&encrypt(h, m), m), h),
+    }
+}
+
+// MD-strengthening: 0x80, zeros, then the 64-bit message length in bits
+pub fn md_padding(data: &[u8]) -> Vec<u8> {
+    let mut padded = data.to_vec();
+    padded.push(0x80);
+    while padded.len() % BLOCK_SIZE != BLOCK_SIZE - LENGTH_SIZE {
+        padded.push(0);
+    }
+    padded.extend(((data.len() as u64) * 8).to_be_bytes());
+
+    padded
+}
+
+// Incremental Merkle-Damgard hash with a 128-bit output
+pub struct AESHash {
+    construction: HashConstruction,
+    state: [u8; BLOCK_SIZE],
+    buffer: Vec<u8>,
+    length: usize,
+}
+
+impl AESHash {
+    pub fn new(construction: HashConstruction) -> AESHash {
+        AESHash {
+            construction,
+            state: HASH_IV,
+            buffer: Vec::with_capacity(BLOCK_SIZE),
+            length: 0,
+        }
+    }
+
+    fn absorb(&mut self, blocks: &[u8]) {
+        for block in blocks.chunks_exact(BLOCK_SIZE) {
+            self.state = compress(self.construction, &self.state, block.try_into().unwrap());
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.length += data.len();
+        self.buffer.extend_from_slice(data);
+
+        let complete = self.buffer.len() / BLOCK_SIZE * BLOCK_SIZE;
+        let blocks: Vec<u8> = self.buffer.drain(..complete).collect();
+        self.absorb(&blocks);
+    }
+
+    pub fn finalize(mut self) -> [u8; BLOCK_SIZE] {
+        // Pad the buffered tail, but with the length of the whole message
+        let mut last = md_padding(&self.buffer);
+        let total = last.len();
+        last[total-LENGTH_SIZE..].copy_from_slice(&((self.length as u64) * 8).to_be_bytes());
+        self.absorb(&last);
+
+        self.state
+    }
+}
+
+pub fn aes_hash(construction: HashConstruction, data: &[u8]) -> [u8; BLOCK_SIZE] {
+    let mut hash = AESHash::new(construction);
+    hash.update(data);
+    hash.finalize()
+}
+
+// AES-256 encryption for the Hirose key H || m, decryption is never needed
+struct AES256 {
+    keys: Vec<[[u8; BYTES_PER_ROW]; BYTES_PER_ROW]>,
+}
+
+impl KeySchedule for AES256 {
+    fn key_schedule(key: &[u8]) -> Result<Vec<[[u8; BYTES_PER_ROW]; BYTES_PER_ROW]>, AESError> {
+        if key.len() != AES256_KEY_SIZE {
+            return Err(AESError::WrongKeySize(key.len(), AES256_KEY_SIZE));
+        }
+        let mut words: Vec<[u8; BYTES_PER_ROW]> = key.chunks_exact(BYTES_PER_ROW)
+            .map(|word| word.try_into().unwrap())
+            .collect();
+        let key_words = words.len();
+
+        for i in key_words..BYTES_PER_ROW*AES256_ROUNDS {
+            let mut word = words[i-1];
+            if i % key_words == 0 {
+                Self::rot_word(&mut word);
+                Self::sub_word(&mut word);
+                Self::r_con(&mut word, i / key_words);
+            } else if i % key_words == 4 {
+                // Extra substitution of the 256-bit schedule
+                Self::sub_word(&mut word);
+            }
+            Self::add_to_column(&mut word, &words[i-key_words]);
+            words.push(word);
+        }
+
+        // Round keys in the same (by-row) view as the state
+        Ok(words.chunks_exact(BYTES_PER_ROW)
+            .map(|columns| array_to_matrix(columns.concat().as_slice().try_into().unwrap()))
+            .collect())
+    }
+}
+
+impl Round for AES256 {}
+
+impl AES256 {
+    fn new(key: &[u8]) -> Result<AES256, AESError> {
+        Ok(AES256 { keys: Self::key_schedule(key)? })
+    }
+
+    fn encrypt_block(&self, block: &[u8; BLOCK_SIZE]) -> [u8; BLOCK_SIZE] {
+        let mut state = array_to_matrix(block);
+        Self::add_round_key(&mut state, &self.keys, 0);
+
+        for round in 1..AES256_ROUNDS-1 {
+            Self::substitute_bytes(&mut state, false);
+            Self::shift_rows(&mut state);
+            Self::mix_columns(&mut state);
+            Self::add_round_key(&mut state, &self.keys, round);
+        }
+
+        // Final round without MixColumns
+        Self::substitute_bytes(&mut state, false);
+        Self::shift_rows(&mut state);
+        Self::add_round_key(&mut state, &self.keys, AES256_ROUNDS-1);
+
+        matrix_to_array(&state)
+    }
+}
+
+// One Hirose step (FSE 2006), the key K = H || m uses the whole 256-bit AES key:
+//   G' = E_K(G) ^ G,  H' = E_K(G ^ c) ^ G ^ c
+pub fn hirose_compress(g: &[u8; BLOCK_SIZE], h: &[u8; BLOCK_SIZE], m: &[u8; BLOCK_SIZE])
+                        -> ([u8; BLOCK_SIZE], [u8; BLOCK_SIZE]) {
+    let cipher = AES256::new(&[&h[..], &m[..]].concat()).unwrap();
+    let g_c = add_iv(g, &HIROSE_CONSTANT);
+
+    (add_iv(&cipher.encrypt_block(g), g), add_iv(&cipher.encrypt_block(&g_c), &g_c))
+}
+
+// Double-block-length hash (Hirose) with a 256-bit output, one message block per step
+pub fn hirose_hash(data: &[u8]) -> [u8; 2*BLOCK_SIZE] {
+    let mut g = HASH_IV;
+    let mut h = [0; BLOCK_SIZE];
+
+    for block in md_padding(data).chunks_exact(BLOCK_SIZE) {
+        (g, h) = hirose_compress(&g, &h, block.try_into().unwrap());
+    }
+
+    let mut digest = [0; 2*BLOCK_SIZE];
+    digest[..BLOCK_SIZE].copy_from_slice(&g);
+    digest[BLOCK_SIZE..].copy_from_slice(&h);
+    digest
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::{aes128::AES128, utils::decode, AES};
+    use super::{aes_hash, compress, hirose_compress, hirose_hash, md_padding, AESHash, HashConstruction, AES256};
+
+    const CONSTRUCTIONS: [HashConstruction; 3] = [
+        HashConstruction::DaviesMeyer,
+        HashConstruction::MatyasMeyerOseas,
+        HashConstruction::MiyaguchiPreneel,
+    ];
+
+    #[test]
+    fn test_md_padding() {
+        for len in [0, 7, 8, 15, 16, 40] {
+            let padded = md_padding(&vec![0x61; len]);
+            assert_eq!(padded.len() % 16, 0);
+            assert_eq!(padded[len], 0x80);
+            assert_eq!(padded[padded.len()-8..], ((len as u64) * 8).to_be_bytes());
+        }
+        // The length no longer fits in the block of "abcdefgh"
+        assert_eq!(md_padding(b"abcdefgh").len(), 32);
+    }
+
+    #[test]
+    fn test_davies_meyer_fixed_point() {
+        // For any m, H = D_m(0) is a fixed point: E_m(H) ^ H = 0 ^ H = H
+        for m in [[0; 16], [0x42; 16], *b"any message blck"] {
+            let h = AES128::new(&m, None).unwrap().decrypt_block(&[0; 16]);
+            assert_eq!(compress(HashConstruction::DaviesMeyer, &h, &m), h);
+        }
+    }
+
+    #[test]
+    fn test_aes_hash() {
+        let mut digests = Vec::new();
+        for construction in CONSTRUCTIONS {
+            let digest = aes_hash(construction, b"abc");
+            assert_eq!(aes_hash(construction, b"abc"), digest);
+            assert_ne!(aes_hash(construction, b"abd"), digest);
+            digests.push(digest);
+        }
+        // Each construction gives its own digest
+        assert_ne!(digests[0], digests[1]);
+        assert_ne!(digests[1], digests[2]);
+        assert_ne!(digests[0], digests[2]);
+    }
+
+    // Digests of the pangram, reference values from an independent model built on Python's AES
+    #[test]
+    fn test_aes_hash_known_answers() {
+        let data = b"The quick brown fox jumps over the lazy dog";
+        let expected = [
+            "04a069193002d68677fbffcfe2855203",
+            "9fe83a131248ff1109696518d666cd9e",
+            "73457320b5866abd722e30dd93462181",
+        ];
+        for (construction, digest) in CONSTRUCTIONS.iter().zip(expected) {
+            assert_eq!(aes_hash(*construction, data).to_vec(), decode(digest).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_aes_hash_incremental() {
+        let data: Vec<u8> = (0..100).collect();
+        for construction in CONSTRUCTIONS {
+            for chunk_size in [1, 9, 16, 33] {
+                let mut hash = AESHash::new(construction);
+                for chunk in data.chunks(chunk_size) {
+                    hash.update(chunk);
+                }
+                assert_eq!(hash.finalize(), aes_hash(construction, &data));
+            }
+        }
+    }
+
+    // FIPS-197, appendix C.3
+    #[test]
+    fn test_aes256() {
+        let key: Vec<u8> = (0..32).collect();
+        let cipher = AES256::new(&key).unwrap();
+        let block = decode("00112233445566778899aabbccddeeff").unwrap().try_into().unwrap();
+        assert_eq!(cipher.encrypt_block(&block).to_vec(), decode("8ea2b7ca516745bfeafc49904b496089").unwrap());
+        assert!(AES256::new(&key[..16]).is_err());
+    }
+
+    // Reference values from an independent model built on Python's AES-256
+    #[test]
+    fn test_hirose_known_answers() {
+        let (g, h) = hirose_compress(&[0x11; 16], &[0x22; 16], &[0x33; 16]);
+        assert_eq!(g.to_vec(), decode("2263428ebc34ea7afbbba43f139ae48f").unwrap());
+        assert_eq!(h.to_vec(), decode("ea556e69994841628ac72b0b7d5b60d6").unwrap());
+
+        let vectors: [(&[u8], &str); 3] = [
+            (b"", "2dd3b8a57bf0f54b1f0d0edaa1a14cb5567e3734177b267ff72aead2e62b4ba5"),
+            (b"abc", "52bb8dea91475b04ff9bfd983fc1c7bb71ebabb5fe790117a3f702ed3b4baea5"),
+            (b"The quick brown fox jumps over the lazy dog",
+             "0c97e9a3239f6c3fe8cd285350fb10203ab4eb61f0f58dafb1476ed136e4e0c7"),
+        ];
+        for (data, digest) in vectors {
+            assert_eq!(hirose_hash(data).to_vec(), decode(digest).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_hirose_hash() {
+        let digest = hirose_hash(b"abc");
+        assert_ne!(hirose_hash(b"abd"), digest);
+        // The two halves come from different encryptions
+        assert_ne!(digest[..16], digest[16..]);
+    }
+}
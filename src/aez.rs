@@ -0,0 +1,507 @@
+use crate::{blake2b::blake2b, round_operations::Round, utils::{add_iv, constant_time_eq, gf128_double}, AESError, BLOCK_SIZE};
+
+// I || J || L
+const KEY_SIZE: usize = 3*BLOCK_SIZE;
+// Shorter inputs use the Feistel-based EncipherTiny
+const CORE_MIN_SIZE: usize = 2*BLOCK_SIZE;
+const TOP_BIT: u128 = 1 << 127;
+
+
+// n * x in GF(2^128), as a sum of doublings
+fn mult(mut n: usize, x: &[u8; BLOCK_SIZE]) -> [u8; BLOCK_SIZE] {
+    let mut result = [0; BLOCK_SIZE];
+    let mut power = *x;
+    while n > 0 {
+        if n & 1 == 1 {
+            result = add_iv(&result, &power);
+        }
+        power = gf128_double(&power);
+        n >>= 1;
+    }
+    result
+}
+
+// data || 10..0, the data is shorter than a block
+fn pad(data: &[u8]) -> [u8; BLOCK_SIZE] {
+    let mut block = [0; BLOCK_SIZE];
+    block[..data.len()].copy_from_slice(data);
+    block[data.len()] = 0x80;
+    block
+}
+
+fn xor(a: &[u8], b: &[u8]) -> Vec<u8> {
+    a.iter().zip(b).map(|(x, y)| x ^ y).collect()
+}
+
+fn block(data: &[u8]) -> [u8; BLOCK_SIZE] {
+    data.try_into().unwrap()
+}
+
+// The first n bits of data starting at bit start, left-aligned in a u128 (n <= 128)
+fn read_bits(data: &[u8], start: usize, n: usize) -> u128 {
+    let mut result = 0;
+    for b in 0..n {
+        let bit = (data[(start + b) / 8] >> (7 - (start + b) % 8)) & 1;
+        result |= (bit as u128) << (127 - b);
+    }
+    result
+}
+
+// Concatenate the first n bits of a and b (2n is a multiple of 8)
+fn join_bits(a: u128, b: u128, n: usize) -> Vec<u8> {
+    let mut result = vec![0; 2 * n / 8];
+    for b_index in 0..2*n {
+        let (value, offset) = if b_index < n { (a, b_index) } else { (b, b_index - n) };
+        let bit = ((value >> (127 - offset)) & 1) as u8;
+        result[b_index / 8] |= bit << (7 - b_index % 8);
+    }
+    result
+}
+
+// AEZ v5 (Hoang, Krovetz, Rogaway): robust authenticated encryption with any expansion tau,
+// a tweakable wide-block cipher built from 4 and 10 AES rounds
+#[allow(clippy::upper_case_acronyms)]
+pub struct AEZ {
+    i: [u8; BLOCK_SIZE],
+    j: [u8; BLOCK_SIZE],
+    l: [u8; BLOCK_SIZE],
+    // 0L, 1L, .., 7L
+    l_multiples: [[u8; BLOCK_SIZE]; 8],
+}
+
+impl Round for AEZ {}
+
+impl AEZ {
+    // Keys of any length: a 48-byte key is used as I || J || L, any other is hashed with BLAKE2b
+    pub fn new(key: &[u8]) -> AEZ {
+        let key = if key.len() == KEY_SIZE { key.to_vec() } else { blake2b(key, KEY_SIZE) };
+        let l = block(&key[2*BLOCK_SIZE..]);
+
+        AEZ {
+            i: block(&key[..BLOCK_SIZE]),
+            j: block(&key[BLOCK_SIZE..2*BLOCK_SIZE]),
+            l,
+            l_multiples: std::array::from_fn(|n| mult(n, &l)),
+        }
+    }
+
+    // AES4 with round keys (J, I, L, 0)
+    fn aes4(&self, x: &[u8; BLOCK_SIZE]) -> [u8; BLOCK_SIZE] {
+        Self::aes_rounds(x, &[self.j, self.i, self.l, [0; BLOCK_SIZE]])
+    }
+
+    // AES10 with round keys (I, J, L, I, J, L, I, J, L, I)
+    fn aes10(&self, x: &[u8; BLOCK_SIZE]) -> [u8; BLOCK_SIZE] {
+        let keys = [self.i, self.j, self.l, self.i, self.j, self.l, self.i, self.j, self.l, self.i];
+        Self::aes_rounds(x, &keys)
+    }
+
+    // Offsets 2^ceil(i/8) I ^ (i mod 8) L ^ jJ for i = 1, 2, ..
+    fn offsets(&self, j: usize) -> impl Iterator<Item = [u8; BLOCK_SIZE]> + '_ {
+        let j_term = mult(j, &self.j);
+        (1..).scan(self.i, move |i_power, i: usize| {
+            if i % 8 == 1 {
+                *i_power = gf128_double(i_power);
+            }
+            Some(add_iv(&add_iv(&j_term, i_power), &self.l_multiples[i % 8]))
+        })
+    }
+
+    // Tweakable block cipher E^{j,i}, j = -1 uses AES10
+    fn e(&self, j: isize, i: usize, x: &[u8; BLOCK_SIZE]) -> [u8; BLOCK_SIZE] {
+        if j == -1 {
+            return self.aes10(&add_iv(x, &mult(i, &self.l)));
+        }
+
+        let mut i_power = self.i;
+        for _ in 0..i.div_ceil(8) {
+            i_power = gf128_double(&i_power);
+        }
+        let delta = add_iv(&add_iv(&mult(j as usize, &self.j), &i_power), &self.l_multiples[i % 8]);
+        self.aes4(&add_iv(x, &delta))
+    }
+
+    // AEZ-hash of the tweak vector (nonce, AD_1, .., AD_k), tau in bits
+    fn hash(&self, tau_bits: usize, tweak: &[&[u8]]) -> [u8; BLOCK_SIZE] {
+        let mut delta = self.e(3, 1, &(tau_bits as u128).to_be_bytes());
+
+        for (index, component) in tweak.iter().enumerate() {
+            let j = index + 4;
+            for (chunk, offset) in component.chunks_exact(BLOCK_SIZE).zip(self.offsets(j)) {
+                delta = add_iv(&delta, &self.aes4(&add_iv(&block(chunk), &offset)));
+            }
+            // Empty components and partial last blocks are padded
+            let rest = &component[component.len() / BLOCK_SIZE * BLOCK_SIZE..];
+            if component.is_empty() || !rest.is_empty() {
+                delta = add_iv(&delta, &self.e(j as isize, 0, &pad(rest)));
+            }
+        }
+
+        delta
+    }
+
+    // AEZ-prf: E^{-1,3}(delta ^ [i]) for i = 0, 1, .., truncated to tau bytes
+    fn prf(&self, delta: &[u8; BLOCK_SIZE], tau: usize) -> Vec<u8> {
+        let mut result = Vec::with_capacity(tau.next_multiple_of(BLOCK_SIZE));
+        let mut i: u128 = 0;
+        while result.len() < tau {
+            result.extend(self.e(-1, 3, &add_iv(delta, &i.to_be_bytes())));
+            i += 1;
+        }
+        result.truncate(tau);
+        result
+    }
+
+    // EncipherCore and DecipherCore only differ by the swapped tweaks 1 and 2
+    fn core(&self, delta: &[u8; BLOCK_SIZE], data: &[u8], inverse: bool) -> Vec<u8> {
+        let (first, second) = if inverse { (2, 1) } else { (1, 2) };
+        let pairs = (data.len() - CORE_MIN_SIZE) / CORE_MIN_SIZE;
+        let (body, tail) = data.split_at(pairs * CORE_MIN_SIZE);
+        let (uv, xy) = tail.split_at(tail.len() - CORE_MIN_SIZE);
+        let (m_x, m_y) = (block(&xy[..BLOCK_SIZE]), block(&xy[BLOCK_SIZE..]));
+        let d = uv.len();
+
+        // First pass over the pairs (M_i, M'_i)
+        let mut ws = Vec::with_capacity(pairs);
+        let mut xs = Vec::with_capacity(pairs);
+        let mut x = [0; BLOCK_SIZE];
+        for (pair, offset) in body.chunks_exact(CORE_MIN_SIZE).zip(self.offsets(1)) {
+            let (m, m_prime) = (block(&pair[..BLOCK_SIZE]), block(&pair[BLOCK_SIZE..]));
+            let w = add_iv(&m, &self.aes4(&add_iv(&m_prime, &offset)));
+            let x_i = add_iv(&m_prime, &self.e(0, 0, &w));
+            x = add_iv(&x, &x_i);
+            ws.push(w);
+            xs.push(x_i);
+        }
+        if d > 0 && d < BLOCK_SIZE {
+            x = add_iv(&x, &self.e(0, 4, &pad(uv)));
+        } else if d >= BLOCK_SIZE {
+            x = add_iv(&x, &self.e(0, 4, &block(&uv[..BLOCK_SIZE])));
+            x = add_iv(&x, &self.e(0, 5, &pad(&uv[BLOCK_SIZE..])));
+        }
+
+        let s_x = add_iv(&add_iv(&add_iv(&m_x, delta), &x), &self.e(0, first, &m_y));
+        let s_y = add_iv(&m_y, &self.e(-1, first, &s_x));
+        let s = add_iv(&s_x, &s_y);
+
+        // Second pass
+        let mut result = Vec::with_capacity(data.len());
+        let mut y = [0; BLOCK_SIZE];
+        let offsets = self.offsets(1).zip(self.offsets(2));
+        for ((w, x_i), (offset1, offset2)) in ws.iter().zip(&xs).zip(offsets) {
+            let s_prime = self.aes4(&add_iv(&s, &offset2));
+            let y_i = add_iv(w, &s_prime);
+            let z_i = add_iv(x_i, &s_prime);
+            let c_prime = add_iv(&y_i, &self.e(0, 0, &z_i));
+            let c = add_iv(&z_i, &self.aes4(&add_iv(&c_prime, &offset1)));
+            result.extend(c);
+            result.extend(c_prime);
+            y = add_iv(&y, &y_i);
+        }
+        if d > 0 && d < BLOCK_SIZE {
+            let c_u = xor(uv, &self.e(-1, 4, &s));
+            y = add_iv(&y, &self.e(0, 4, &pad(&c_u)));
+            result.extend(c_u);
+        } else if d >= BLOCK_SIZE {
+            let c_u = add_iv(&block(&uv[..BLOCK_SIZE]), &self.e(-1, 4, &s));
+            let c_v = xor(&uv[BLOCK_SIZE..], &self.e(-1, 5, &s));
+            y = add_iv(&y, &self.e(0, 4, &c_u));
+            y = add_iv(&y, &self.e(0, 5, &pad(&c_v)));
+            result.extend(c_u);
+            result.extend(c_v);
+        }
+
+        let c_y = add_iv(&s_x, &self.e(-1, second, &s_y));
+        let c_x = add_iv(&add_iv(&add_iv(&s_y, delta), &y), &self.e(0, second, &c_y));
+        result.extend(c_x);
+        result.extend(c_y);
+
+        result
+    }
+
+    // Flip the first bit depending on all the others (an involution), only for m < 128 bits
+    fn tiny_first_bit(&self, delta: &[u8; BLOCK_SIZE], data: &[u8]) -> Vec<u8> {
+        let value = read_bits(data, 0, data.len() * 8);
+        let input = u128::from_be_bytes(*delta) ^ (value | TOP_BIT);
+        let bit = u128::from_be_bytes(self.e(0, 3, &input.to_be_bytes())) & TOP_BIT;
+
+        let mut result = data.to_vec();
+        result[0] ^= (bit >> 120) as u8;
+        result
+    }
+
+    // EncipherTiny and DecipherTiny: balanced Feistel network on two halves of m/2 bits
+    fn tiny(&self, delta: &[u8; BLOCK_SIZE], data: &[u8], inverse: bool) -> Vec<u8> {
+        let m = data.len() * 8;
+        let n = m / 2;
+        let rounds = match m {
+            8 => 24,
+            16 => 16,
+            _ if m < 128 => 10,
+            _ => 8,
+        };
+        let tweak = if m >= 128 { 6 } else { 7 };
+        let mask = !0u128 << (128 - n);
+        let delta_value = u128::from_be_bytes(*delta);
+
+        let data = if inverse && m < 128 { self.tiny_first_bit(delta, data) } else { data.to_vec() };
+        let mut left = read_bits(&data, 0, n);
+        let mut right = read_bits(&data, n, n);
+        for k in 0..rounds {
+            let i = if inverse { rounds - 1 - k } else { k };
+            // Delta ^ R10* ^ [i]
+            let input = delta_value ^ right ^ (1 << (127 - n)) ^ i as u128;
+            let f = u128::from_be_bytes(self.e(0, tweak, &input.to_be_bytes())) & mask;
+            (left, right) = (right, left ^ f);
+        }
+
+        let result = join_bits(right, left, n);
+        if !inverse && m < 128 { self.tiny_first_bit(delta, &result) } else { result }
+    }
+
+    fn encipher_with(&self, delta: &[u8; BLOCK_SIZE], data: &[u8], inverse: bool) -> Vec<u8> {
+        match data.len() {
+            0 => Vec::new(),
+            len if len < CORE_MIN_SIZE => self.tiny(delta, data, inverse),
+            _ => self.core(delta, data, inverse),
+        }
+    }
+
+    // Length-preserving tweakable encipherment (no expansion, no authentication)
+    pub fn encipher(&self, tweak: &[&[u8]], data: &[u8]) -> Vec<u8> {
+        self.encipher_with(&self.hash(0, tweak), data, false)
+    }
+
+    pub fn decipher(&self, tweak: &[&[u8]], data: &[u8]) -> Vec<u8> {
+        self.encipher_with(&self.hash(0, tweak), data, true)
+    }
+
+    // Encipher M || 0^tau, the ciphertext is tau bytes longer than the plaintext
+    pub fn encrypt(&self, nonce: &[u8], ad: &[&[u8]], tau: usize, plaintext: &[u8]) -> Vec<u8> {
+        let tweak: Vec<&[u8]> = [nonce].into_iter().chain(ad.iter().copied()).collect();
+        let delta = self.hash(tau * 8, &tweak);
+
+        if plaintext.is_empty() {
+            return self.prf(&delta, tau);
+        }
+        let mut data = plaintext.to_vec();
+        data.resize(plaintext.len() + tau, 0);
+        self.encipher_with(&delta, &data, false)
+    }
+
+    // Decipher and check that the last tau bytes are zero
+    pub fn decrypt(&self, nonce: &[u8], ad: &[&[u8]], tau: usize, ciphertext: &[u8]) -> Result<Vec<u8>, AESError> {
+        if ciphertext.len() < tau {
+            return Err(AESError::DataTooShort(ciphertext.len(), tau));
+        }
+        let tweak: Vec<&[u8]> = [nonce].into_iter().chain(ad.iter().copied()).collect();
+        let delta = self.hash(tau * 8, &tweak);
+
+        if ciphertext.len() == tau {
+            if !constant_time_eq(&self.prf(&delta, tau), ciphertext) {
+                return Err(AESError::AuthenticationFailed);
+            }
+            return Ok(Vec::new());
+        }
+
+        let mut data = self.encipher_with(&delta, ciphertext, true);
+        let zeros = data.split_off(ciphertext.len() - tau);
+        if !constant_time_eq(&zeros, &vec![0; tau]) {
+            return Err(AESError::AuthenticationFailed);
+        }
+
+        Ok(data)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use crate::{utils::decode, AESError};
+    use super::AEZ;
+
+    fn key() -> Vec<u8> {
+        (0..48).collect()
+    }
+
+    // Bytes 0, 3, 6, ..
+    fn message(size: usize) -> Vec<u8> {
+        (0..size).map(|i| (3 * i) as u8).collect()
+    }
+
+    // The official aez-test-vectors are not at hand: these known answers come from a separate
+    // model of the AEZ v5 spec in Python (hashlib's BLAKE2b, an AES round checked against OpenSSL),
+    // they pin every path against regressions but not against a misreading of the spec
+    #[test]
+    fn test_aez_extract_known_answers() {
+        let vectors: [(&[u8], &str); 2] = [
+            (b"", "b32811423377f52d7862286ee1a72ee540524380fda1724a6f25d7978c6fd324\
+                   4a6caf0498812673c5e05ef583825100"),
+            (&key()[..16], "7dbfe0cf53262764ca067721002a7a16a10d6cb22f6dc554591ee8e008e1bd26\
+                            cfcd125de7be3ef5a80fe5e5a7c9ea34"),
+        ];
+        for (k, expected) in vectors {
+            let aez = AEZ::new(k);
+            assert_eq!([aez.i, aez.j, aez.l].concat(), decode(expected).unwrap());
+        }
+
+        // A 48-byte key is used as it is
+        let aez = AEZ::new(&key());
+        assert_eq!([aez.i, aez.j, aez.l].concat(), key());
+    }
+
+    #[test]
+    fn test_aez_hash_and_prf_known_answers() {
+        let aez = AEZ::new(&key());
+        let nonce = message(12);
+        let (short, long, block) = (message(7), message(33), message(16));
+        let vectors: [(usize, Vec<&[u8]>, &str); 5] = [
+            (0, vec![], "eeffe71cbb95bb894daf7baf9804fcae"),
+            (0, vec![b""], "4c44bf5117f9eeb2c87969a96c1ea9f9"),
+            (128, vec![&nonce], "247acea07e33d006b7e97ced56083639"),
+            (160, vec![&nonce, &short, &long], "75849a1c0f6b7448a18c99e978dc5242"),
+            (32, vec![&block, b""], "b71bd34b7e5f3c4a72257879c068d4ab"),
+        ];
+        for (tau_bits, tweak, expected) in vectors {
+            assert_eq!(aez.hash(tau_bits, &tweak).to_vec(), decode(expected).unwrap());
+        }
+
+        let delta = message(16).try_into().unwrap();
+        assert_eq!(aez.prf(&delta, 4), decode("eca1f488").unwrap());
+        assert_eq!(aez.prf(&delta, 20), decode("eca1f488a91ec2a87ba3c8fa840adabd8c2b468b").unwrap());
+    }
+
+    #[test]
+    fn test_aez_encipher_known_answers() {
+        let aez = AEZ::new(&key());
+        let nonce = message(12);
+        // EncipherTiny below 32 bytes (with the extra bit below 16 bytes), EncipherCore above
+        let vectors = [
+            (1, "ce"),
+            (2, "d7fc"),
+            (7, "e5f004f9c120d1"),
+            (16, "2e78a6e58e41ecebbccc2835623edde6"),
+            (31, "4b155d1f7b5343f5e228f4fdf74d22873c7ae4bc9028cfe8502beb35c34e5b"),
+            (32, "2b5588303e85f0a1e15e0aee9a8d402eff9a4a905256bfca638dffc4dfb1657c"),
+            (33, "c2c888ac99ba0be92771c9405333c1960aa3f1a0f0eb4cb1d5c2480dc5e04dd636"),
+            (47, "4067e9ac54e99c884677bfdbfe39409d522b8289fbb7f85906bf77f11b4a35d1\
+                  0a685c1785bc4535df55067069d98a"),
+            (48, "878d14cb474f43c24e0da025b621ef7d892ac5e0fb75bf3eabe067039ed163be\
+                  ebdfc89ed124a5afb3646e9cd5321989"),
+            (64, "c157f7887579d6f5102ad3559070fb004429db8bdabc455867d6cf0cc5dcf1b0\
+                  354b984a86900c0d41b76746d1959cc212eac3da90c4267debc44b04eee5acee"),
+            (100, "5ea57d912e01ff8f644808e9e97c162656294f447134f74cb2be60fa2e91f8b0\
+                   3d222520416bf0d55144551d5614897e344b250135e399254d8553d0178c1e6d\
+                   d2431772b64c489a8022e7d7f2ecae0eb964e6cfb01a944d8e3a0d82e69659f6\
+                   1199ac9d"),
+        ];
+        for (len, expected) in vectors {
+            let enciphered = aez.encipher(&[&nonce], &message(len));
+            assert_eq!(enciphered, decode(expected).unwrap());
+            assert_eq!(aez.decipher(&[&nonce], &enciphered), message(len));
+        }
+    }
+
+    #[test]
+    fn test_aez_encrypt_known_answers() {
+        let aez = AEZ::new(&key());
+        let (nonce, ad) = (message(12), message(5));
+        // The empty message goes through AEZ-prf
+        let vectors = [
+            (16, 0, "c7b95448b7f4b311d4b2753497538a9e"),
+            (4, 0, "6fc546e9"),
+            (16, 1, "a0c36480350638d21860a16ff5a26f3238"),
+            (0, 15, "6684329e62355cdf094d767c5f4fa1"),
+            (4, 20, "2f62391f090a17c78d99800ae96cab00c5a7846931b6fb08"),
+            (16, 40, "b7a4190c40ff8e0d21e2fb7a87639ab7a62a768df4575c8cfa294b5cab06fdc2\
+                      a154a34713761d7802dde1b7e49ad7f957c4b84c90c1878a"),
+            (20, 63, "8bde8d7f40f3ec5ad4d38271aa2af66605fd4dc74c95b02ab215c2f947e9bb68\
+                      16312725b794e79fc329747e6aeae4255fa0d6ecf788f9872e778fa02a976ff0\
+                      79f957f92fe4f688e7cf9a5531d35c74806b8b"),
+        ];
+        for (tau, len, expected) in vectors {
+            let ciphertext = aez.encrypt(&nonce, &[&ad], tau, &message(len));
+            assert_eq!(ciphertext, decode(expected).unwrap());
+            assert_eq!(aez.decrypt(&nonce, &[&ad], tau, &ciphertext).unwrap(), message(len));
+        }
+    }
+
+    #[test]
+    fn test_aez_encrypt_decrypt() {
+        let aez = AEZ::new(&key());
+        let ad: [&[u8]; 2] = [b"header", b""];
+
+        // Empty, tiny (< 32 bytes) and core inputs with every size of the last part
+        for len in [0, 1, 2, 3, 15, 16, 17, 31, 32, 33, 47, 48, 49, 63, 64, 65, 100, 300] {
+            let plaintext: Vec<u8> = (0..len).map(|i| i as u8).collect();
+            for tau in [0, 1, 4, 16, 20] {
+                let ciphertext = aez.encrypt(b"nonce", &ad, tau, &plaintext);
+                assert_eq!(ciphertext.len(), len + tau);
+                assert_eq!(aez.decrypt(b"nonce", &ad, tau, &ciphertext).unwrap(), plaintext);
+            }
+        }
+    }
+
+    #[test]
+    fn test_aez_key_extraction() {
+        // Any key length works, a different key gives a different ciphertext
+        let a = AEZ::new(b"short key").encrypt(b"", &[], 16, b"message");
+        let b = AEZ::new(b"short kez").encrypt(b"", &[], 16, b"message");
+        let c = AEZ::new(&key()).encrypt(b"", &[], 16, b"message");
+        assert_ne!(a, b);
+        assert_ne!(a, c);
+        assert_eq!(AEZ::new(b"short key").decrypt(b"", &[], 16, &a).unwrap(), b"message");
+    }
+
+    #[test]
+    fn test_aez_authentication() {
+        let aez = AEZ::new(&key());
+        for len in [0, 5, 40] {
+            let plaintext = vec![0x61; len];
+            let ciphertext = aez.encrypt(b"nonce", &[b"ad"], 16, &plaintext);
+
+            // Any modified byte is detected
+            for position in 0..ciphertext.len() {
+                let mut tampered = ciphertext.clone();
+                tampered[position] ^= 0x01;
+                assert!(matches!(aez.decrypt(b"nonce", &[b"ad"], 16, &tampered), Err(AESError::AuthenticationFailed)));
+            }
+            // So is a different nonce or associated data
+            assert!(aez.decrypt(b"nonce2", &[b"ad"], 16, &ciphertext).is_err());
+            assert!(aez.decrypt(b"nonce", &[b"ad", b""], 16, &ciphertext).is_err());
+        }
+        assert!(matches!(aez.decrypt(b"nonce", &[], 16, &[0; 10]), Err(AESError::DataTooShort(10, 16))));
+    }
+
+    #[test]
+    fn test_aez_encipher() {
+        let aez = AEZ::new(&key());
+        let data = vec![0; 64];
+        let enciphered = aez.encipher(&[b"sector 7"], &data);
+        assert_eq!(enciphered.len(), 64);
+        assert_eq!(aez.decipher(&[b"sector 7"], &enciphered), data);
+        // Same as encrypt without expansion
+        assert_eq!(aez.encrypt(b"sector 7", &[], 0, &data), enciphered);
+
+        // Wide-block: one changed bit anywhere changes (nearly) every byte
+        for position in [0, 20, 40, 63] {
+            let mut modified = data.clone();
+            modified[position] ^= 0x80;
+            let other = aez.encipher(&[b"sector 7"], &modified);
+            assert!(enciphered.iter().zip(&other).filter(|(a, b)| a != b).count() > 56);
+        }
+    }
+
+    #[test]
+    fn test_aez_tiny_permutation() {
+        // The 1-byte cipher is a permutation of all 256 values
+        let aez = AEZ::new(&key());
+        let outputs: HashSet<Vec<u8>> = (0..=255u8).map(|b| aez.encipher(&[], &[b])).collect();
+        assert_eq!(outputs.len(), 256);
+
+        for b in [0u8, 1, 0x80, 0xff] {
+            assert_eq!(aez.decipher(&[], &aez.encipher(&[], &[b])), [b]);
+        }
+    }
+}
@@ -0,0 +1,116 @@
+// BLAKE2b (RFC 7693) without key, only needed to extract the AEZ key
+pub const MAX_DIGEST_SIZE: usize = 64;
+const BLAKE2B_BLOCK_SIZE: usize = 128;
+const ROUNDS: usize = 12;
+
+const IV: [u64; 8] = [
+    0x6a09e667f3bcc908, 0xbb67ae8584caa73b, 0x3c6ef372fe94f82b, 0xa54ff53a5f1d36f1,
+    0x510e527fade682d1, 0x9b05688c2b3e6c1f, 0x1f83d9abfb41bd6b, 0x5be0cd19137e2179,
+];
+
+const SIGMA: [[usize; 16]; 10] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+    [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+    [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+    [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+    [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+    [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+    [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+    [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+    [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0],
+];
+
+
+// Mixing function G
+fn mix(v: &mut [u64; 16], a: usize, b: usize, c: usize, d: usize, x: u64, y: u64) {
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(x);
+    v[d] = (v[d] ^ v[a]).rotate_right(32);
+    v[c] = v[c].wrapping_add(v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(24);
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(y);
+    v[d] = (v[d] ^ v[a]).rotate_right(16);
+    v[c] = v[c].wrapping_add(v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(63);
+}
+
+fn compress(h: &mut [u64; 8], block: &[u8], counter: u128, last: bool) {
+    let mut m = [0u64; 16];
+    for i in 0..16 {
+        m[i] = u64::from_le_bytes(block[8*i..8*i+8].try_into().unwrap());
+    }
+
+    let mut v = [0u64; 16];
+    v[..8].copy_from_slice(h);
+    v[8..].copy_from_slice(&IV);
+    v[12] ^= counter as u64;
+    v[13] ^= (counter >> 64) as u64;
+    if last {
+        v[14] = !v[14];
+    }
+
+    for round in 0..ROUNDS {
+        let s = &SIGMA[round % 10];
+        mix(&mut v, 0, 4, 8, 12, m[s[0]], m[s[1]]);
+        mix(&mut v, 1, 5, 9, 13, m[s[2]], m[s[3]]);
+        mix(&mut v, 2, 6, 10, 14, m[s[4]], m[s[5]]);
+        mix(&mut v, 3, 7, 11, 15, m[s[6]], m[s[7]]);
+        mix(&mut v, 0, 5, 10, 15, m[s[8]], m[s[9]]);
+        mix(&mut v, 1, 6, 11, 12, m[s[10]], m[s[11]]);
+        mix(&mut v, 2, 7, 8, 13, m[s[12]], m[s[13]]);
+        mix(&mut v, 3, 4, 9, 14, m[s[14]], m[s[15]]);
+    }
+
+    for i in 0..8 {
+        h[i] ^= v[i] ^ v[i+8];
+    }
+}
+
+// Digest of 1 to 64 bytes
+pub fn blake2b(data: &[u8], digest_size: usize) -> Vec<u8> {
+    assert!((1..=MAX_DIGEST_SIZE).contains(&digest_size), "BLAKE2b digest size must be 1..=64");
+
+    // Parameter block: digest length, no key, fanout and depth 1
+    let mut h = IV;
+    h[0] ^= 0x01010000 ^ digest_size as u64;
+
+    // The last block (possibly empty or partial) is compressed with the final flag
+    let blocks = if data.is_empty() { 0 } else { (data.len() - 1) / BLAKE2B_BLOCK_SIZE };
+    for i in 0..blocks {
+        let block = &data[i*BLAKE2B_BLOCK_SIZE..(i+1)*BLAKE2B_BLOCK_SIZE];
+        compress(&mut h, block, ((i+1)*BLAKE2B_BLOCK_SIZE) as u128, false);
+    }
+    let mut last = [0; BLAKE2B_BLOCK_SIZE];
+    let rest = &data[blocks*BLAKE2B_BLOCK_SIZE..];
+    last[..rest.len()].copy_from_slice(rest);
+    compress(&mut h, &last, data.len() as u128, true);
+
+    let mut digest: Vec<u8> = h.iter().flat_map(|word| word.to_le_bytes()).collect();
+    digest.truncate(digest_size);
+    digest
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::decode;
+    use super::blake2b;
+
+    #[test]
+    fn test_blake2b() {
+        // RFC 7693, Appendix A
+        assert_eq!(blake2b(b"abc", 64), decode("ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d1\
+                                               7d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923").unwrap());
+
+        // 48-byte digests as used by AEZ, around the block boundary
+        let data: Vec<u8> = (0..200).collect();
+        let examples = [
+            (0, "b32811423377f52d7862286ee1a72ee540524380fda1724a6f25d7978c6fd3244a6caf0498812673c5e05ef583825100"),
+            (128, "a2c2acf7ce4079c02b7f38e2ef33bff531a31a7c7effe712c5348b4d616c0cba9b152679317984ec632d0c70eb11eece"),
+            (200, "c3fb89d604f306fc6ee2aafebefbf69d26b21dbbdc055166858d527a4501ff479894b533398334379c182ad6747bd1af"),
+        ];
+        for (len, expected) in examples {
+            assert_eq!(blake2b(&data[..len], 48), decode(expected).unwrap());
+        }
+    }
+}
@@ -0,0 +1,175 @@
+use crate::{utils::split_in_blocks, AESError, AESMode, AES, BLOCK_SIZE};
+
+
+// Ciphertext stealing variants for CBC (SP 800-38A addendum), they differ only
+// in the order of the last two ciphertext blocks
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CiphertextStealing {
+    // Partial block C*_{n-1} comes before the last full block C_n
+    CS1,
+    // Like CS1 when the data is block aligned, otherwise like CS3
+    CS2,
+    // Last two blocks are always swapped (Kerberos, RFC 3962)
+    CS3,
+}
+
+impl CiphertextStealing {
+    // Whether C_n comes before C*_{n-1}, given the size of the last partial block
+    fn swapped(&self, last_size: usize) -> bool {
+        match self {
+            CiphertextStealing::CS1 => false,
+            CiphertextStealing::CS2 => last_size != BLOCK_SIZE,
+            CiphertextStealing::CS3 => true,
+        }
+    }
+}
+
+// Size of the last (possibly partial) block
+fn last_block_size(len: usize) -> usize {
+    len - (len.div_ceil(BLOCK_SIZE) - 1) * BLOCK_SIZE
+}
+
+// Encrypt with CBC (using the IV of the cipher) without padding: the ciphertext
+// has the same length as the data, which must be at least one block
+pub fn encrypt_cbc_cs<A: AES>(cipher: &A, data: &[u8], variant: CiphertextStealing)
+                        -> Result<Vec<u8>, AESError> {
+    if data.len() < BLOCK_SIZE {
+        return Err(AESError::DataTooShort(data.len(), BLOCK_SIZE));
+    }
+    let d = last_block_size(data.len());
+
+    // Pad the last block with zeros and run plain CBC
+    let mut padded = data.to_vec();
+    padded.resize(data.len().div_ceil(BLOCK_SIZE) * BLOCK_SIZE, 0);
+    let blocks = cipher.encrypt_blocks(&split_in_blocks(&padded)?, AESMode::CBC)?;
+    let n = blocks.len();
+
+    // Only the first d bytes of C_{n-1} are needed, the rest can be recovered from C_n
+    let mut result: Vec<u8> = blocks[..n-1].iter().flatten().copied().collect();
+    result.truncate(data.len() - BLOCK_SIZE);
+    // A single block has nothing to swap with
+    if n > 1 && variant.swapped(d) {
+        let partial = result.split_off(result.len() - d);
+        result.extend_from_slice(&blocks[n-1]);
+        result.extend(partial);
+    } else {
+        result.extend_from_slice(&blocks[n-1]);
+    }
+
+    Ok(result)
+}
+
+pub fn decrypt_cbc_cs<A: AES>(cipher: &A, data: &[u8], variant: CiphertextStealing)
+                        -> Result<Vec<u8>, AESError> {
+    if data.len() < BLOCK_SIZE {
+        return Err(AESError::DataTooShort(data.len(), BLOCK_SIZE));
+    }
+    // A single block is plain CBC
+    if data.len() == BLOCK_SIZE {
+        let blocks = cipher.decrypt_blocks(&split_in_blocks(data)?, AESMode::CBC)?;
+        return Ok(blocks[0].to_vec());
+    }
+    let d = last_block_size(data.len());
+    let head = data.len() - BLOCK_SIZE - d;
+
+    // Bring the data back to the CS1 order: C_1 .. C_{n-2} || C*_{n-1} || C_n
+    let mut ordered = data[..head].to_vec();
+    if variant.swapped(d) {
+        ordered.extend_from_slice(&data[head+BLOCK_SIZE..]);
+        ordered.extend_from_slice(&data[head..head+BLOCK_SIZE]);
+    } else {
+        ordered.extend_from_slice(&data[head..]);
+    }
+
+    // D(C_n) = P_n || 0..0 xor C_{n-1}, so its tail is the missing tail of C_{n-1}
+    let last: [u8; BLOCK_SIZE] = ordered[ordered.len()-BLOCK_SIZE..].try_into().unwrap();
+    let decrypted_last = cipher.decrypt_block(&last);
+    let mut full = ordered[..ordered.len()-BLOCK_SIZE].to_vec();
+    full.extend_from_slice(&decrypted_last[d..]);
+    full.extend_from_slice(&last);
+
+    // Now it is plain CBC, the zero padding is dropped at the end
+    let blocks = cipher.decrypt_blocks(&split_in_blocks(&full)?, AESMode::CBC)?;
+    let mut result: Vec<u8> = blocks.into_iter().flatten().collect();
+    result.truncate(data.len());
+
+    Ok(result)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::{aes128::AES128, utils::decode, AESError, AESMode, AES};
+    use super::{decrypt_cbc_cs, encrypt_cbc_cs, CiphertextStealing};
+
+    const PLAINTEXT: &str = "4920776f756c64206c696b65207468652047656e6572616c20476175277320\
+                            436869636b656e2c20706c656173652c20616e6420776f6e746f6e20736f75702e";
+
+    #[test]
+    fn test_cs3_vectors() {
+        // RFC 3962 appendix B (Kerberos AES-CTS is CBC-CS3 with a zero IV)
+        let key = decode("636869636b656e207465726979616b69").unwrap();
+        let aes = AES128::new(&key, Some([0; 16])).unwrap();
+        let plaintext = decode(PLAINTEXT).unwrap();
+        let vectors = [
+            (17, "c6353568f2bf8cb4d8a580362da7ff7f97"),
+            (31, "fc00783e0efdb2c1d445d4c8eff7ed2297687268d6ecccc0c07b25e25ecfe5"),
+            (32, "39312523a78662d5be7fcbcc98ebf5a897687268d6ecccc0c07b25e25ecfe584"),
+            (47, "97687268d6ecccc0c07b25e25ecfe584b3fffd940c16a18c1b5549d2f838029e39312523a78662d5be7fcbcc98ebf5"),
+            (48, "97687268d6ecccc0c07b25e25ecfe5849dad8bbb96c4cdc03bc103e1a194bbd839312523a78662d5be7fcbcc98ebf5a8"),
+            (64, "97687268d6ecccc0c07b25e25ecfe58439312523a78662d5be7fcbcc98ebf5a8\
+                    4807efe836ee89a526730dbc2f7bc8409dad8bbb96c4cdc03bc103e1a194bbd8"),
+        ];
+
+        for (len, expected) in vectors {
+            let result = encrypt_cbc_cs(aes.as_ref(), &plaintext[..len], CiphertextStealing::CS3).unwrap();
+            assert_eq!(result, decode(expected).unwrap());
+            assert_eq!(decrypt_cbc_cs(aes.as_ref(), &result, CiphertextStealing::CS3).unwrap(), &plaintext[..len]);
+        }
+    }
+
+    #[test]
+    fn test_cs1_cs2_vectors() {
+        // Same data as RFC 3962, the variants only differ in the order of the last two blocks
+        let key = decode("636869636b656e207465726979616b69").unwrap();
+        let aes = AES128::new(&key, Some([0; 16])).unwrap();
+        let plaintext = decode(PLAINTEXT).unwrap();
+
+        let cs1 = encrypt_cbc_cs(aes.as_ref(), &plaintext[..17], CiphertextStealing::CS1).unwrap();
+        assert_eq!(cs1, decode("97c6353568f2bf8cb4d8a580362da7ff7f").unwrap());
+        let cs2 = encrypt_cbc_cs(aes.as_ref(), &plaintext[..17], CiphertextStealing::CS2).unwrap();
+        assert_eq!(cs2, decode("c6353568f2bf8cb4d8a580362da7ff7f97").unwrap());
+
+        // Block aligned data: CS1 and CS2 are plain CBC
+        let cbc = aes.encrypt_blocks(&[plaintext[..16].try_into().unwrap(), plaintext[16..32].try_into().unwrap()],
+                                    AESMode::CBC).unwrap();
+        let cbc: Vec<u8> = cbc.into_iter().flatten().collect();
+        assert_eq!(encrypt_cbc_cs(aes.as_ref(), &plaintext[..32], CiphertextStealing::CS1).unwrap(), cbc);
+        assert_eq!(encrypt_cbc_cs(aes.as_ref(), &plaintext[..32], CiphertextStealing::CS2).unwrap(), cbc);
+    }
+
+    #[test]
+    fn test_cbc_cs_all_lengths() {
+        let aes = AES128::new(&[0x42; 16], Some([0x24; 16])).unwrap();
+        for variant in [CiphertextStealing::CS1, CiphertextStealing::CS2, CiphertextStealing::CS3] {
+            for len in 16..=64 {
+                let data: Vec<u8> = (0..len).map(|i| i as u8).collect();
+                let encrypted = encrypt_cbc_cs(aes.as_ref(), &data, variant).unwrap();
+
+                assert_eq!(encrypted.len(), data.len());
+                assert_eq!(decrypt_cbc_cs(aes.as_ref(), &encrypted, variant).unwrap(), data);
+            }
+        }
+    }
+
+    #[test]
+    fn test_cbc_cs_errors() {
+        let aes = AES128::new(&[0x42; 16], Some([0x24; 16])).unwrap();
+        assert!(matches!(encrypt_cbc_cs(aes.as_ref(), &[0; 15], CiphertextStealing::CS1),
+                        Err(AESError::DataTooShort(15, 16))));
+
+        let aes = AES128::new(&[0x42; 16], None).unwrap();
+        assert!(matches!(encrypt_cbc_cs(aes.as_ref(), &[0; 20], CiphertextStealing::CS1),
+                        Err(AESError::ModeRequiresIV(AESMode::CBC))));
+    }
+}
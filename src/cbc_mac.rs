@@ -0,0 +1,115 @@
+use crate::{utils::{constant_time_eq, split_in_blocks}, AESError, AESMode, AES, BLOCK_SIZE};
+
+
+// Plain CBC-MAC is only secure when every message has the same length
+#[derive(Debug, Clone, Copy)]
+pub enum CBCMACMode {
+    // Accept only messages of exactly this many bytes (a multiple of the block size)
+    FixedLength(usize),
+    // ISO/IEC 9797-1 padding method 3: prepend a block with the bit length, then pad with zeros
+    LengthPrepended,
+}
+
+// CBC-MAC built on CBC encryption with a zero IV, the tag is the last ciphertext block
+#[allow(clippy::upper_case_acronyms)]
+pub struct CBCMAC<A: AES> {
+    cipher: Box<A>,
+    mode: CBCMACMode,
+}
+
+impl<A: AES> CBCMAC<A> {
+    pub fn new(key: &[u8], mode: CBCMACMode) -> Result<CBCMAC<A>, AESError> {
+        if let CBCMACMode::FixedLength(len) = mode {
+            if len == 0 || len % BLOCK_SIZE != 0 {
+                return Err(AESError::DataNotDivisibleInBlocks(len, BLOCK_SIZE));
+            }
+        }
+        let cipher = A::new(key, Some([0; BLOCK_SIZE]))?;
+
+        Ok(CBCMAC { cipher, mode })
+    }
+
+    // Prepare the blocks to be chained, depending on the mode
+    fn blocks(&self, data: &[u8]) -> Result<Vec<[u8; BLOCK_SIZE]>, AESError> {
+        match self.mode {
+            CBCMACMode::FixedLength(len) => {
+                if data.len() != len {
+                    return Err(AESError::WrongMessageLength(data.len(), len));
+                }
+                split_in_blocks(data)
+            },
+            CBCMACMode::LengthPrepended => {
+                let mut padded = vec![0; BLOCK_SIZE];
+                padded[BLOCK_SIZE-8..].copy_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+                padded.extend_from_slice(data);
+                padded.resize(padded.len().div_ceil(BLOCK_SIZE) * BLOCK_SIZE, 0);
+                split_in_blocks(&padded)
+            }
+        }
+    }
+
+    pub fn mac(&self, data: &[u8]) -> Result<[u8; BLOCK_SIZE], AESError> {
+        let blocks = self.blocks(data)?;
+        let encrypted = self.cipher.encrypt_blocks(&blocks, AESMode::CBC)?;
+
+        // There is at least one block in both modes
+        Ok(*encrypted.last().unwrap())
+    }
+
+    pub fn verify(&self, data: &[u8], tag: &[u8]) -> Result<(), AESError> {
+        if !constant_time_eq(&self.mac(data)?, tag) {
+            return Err(AESError::AuthenticationFailed);
+        }
+
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::{aes128::AES128, utils::decode, AESError};
+    use super::{CBCMACMode, CBCMAC};
+
+    const KEY: &str = "2b7e151628aed2a6abf7158809cf4f3c";
+
+    #[test]
+    fn test_cbc_mac_fixed_length() {
+        // First two SP 800-38A plaintext blocks, chained from a zero IV
+        // (the SP 800-38A CBC example itself uses IV 000102..0f)
+        let key = decode(KEY).unwrap();
+        let data = decode("6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51").unwrap();
+        let mac = CBCMAC::<AES128>::new(&key, CBCMACMode::FixedLength(32)).unwrap();
+
+        let expected = decode("b148c17f309ee692287ae57cf12add49").unwrap();
+        assert_eq!(mac.mac(&data).unwrap().to_vec(), expected);
+        assert!(mac.verify(&data, &expected).is_ok());
+    }
+
+    #[test]
+    fn test_cbc_mac_fixed_length_errors() {
+        let key = decode(KEY).unwrap();
+        let mac = CBCMAC::<AES128>::new(&key, CBCMACMode::FixedLength(32)).unwrap();
+
+        assert!(matches!(mac.mac(&[0; 16]), Err(AESError::WrongMessageLength(16, 32))));
+        assert!(matches!(mac.mac(&[0; 48]), Err(AESError::WrongMessageLength(48, 32))));
+        assert!(matches!(CBCMAC::<AES128>::new(&key, CBCMACMode::FixedLength(20)),
+                        Err(AESError::DataNotDivisibleInBlocks(20, 16))));
+    }
+
+    #[test]
+    fn test_cbc_mac_length_prepended() {
+        let key = decode(KEY).unwrap();
+        let mac = CBCMAC::<AES128>::new(&key, CBCMACMode::LengthPrepended).unwrap();
+
+        // Zero padding alone would make these two messages collide
+        let short = mac.mac(b"message").unwrap();
+        let padded = mac.mac(b"message\0").unwrap();
+        assert_ne!(short, padded);
+        assert!(mac.verify(b"message", &short).is_ok());
+        assert!(matches!(mac.verify(b"message\0", &short), Err(AESError::AuthenticationFailed)));
+
+        // Empty message is accepted
+        assert!(mac.mac(b"").is_ok());
+    }
+}
@@ -0,0 +1,89 @@
+// Educational module: why naive CBC-MAC must not be used on messages of variable length.
+//
+// With tag(M) = last block of CBC_K(M) and a zero IV, knowing t1 = tag(m1) and t2 = tag(m2)
+// for two one-block messages is enough to compute a valid tag for m1 || (m2 ^ t1):
+// the second block becomes E(t1 ^ m2 ^ t1) = E(m2) = t2, without knowing the key.
+use crate::{utils::{add_iv, split_in_blocks}, AESError, AESMode, AES, BLOCK_SIZE};
+
+
+// Naive CBC-MAC over any length: zero padding, no length information.
+// The cipher must be created with a zero IV
+pub fn naive_cbc_mac<A: AES>(cipher: &A, data: &[u8]) -> Result<[u8; BLOCK_SIZE], AESError> {
+    let blocks = split_in_blocks(&zero_padding(data))?;
+    let encrypted = cipher.encrypt_blocks(&blocks, AESMode::CBC)?;
+
+    // There is always at least one block
+    Ok(*encrypted.last().unwrap())
+}
+
+// Build a message whose tag is t2 from a tagged message (m1, t1) and a tagged one-block message m2
+pub fn forge_extension(m1: &[u8], t1: &[u8; BLOCK_SIZE], m2: &[u8; BLOCK_SIZE]) -> Vec<u8> {
+    let mut forged = zero_padding(m1);
+    forged.extend_from_slice(&add_iv(m2, t1));
+    forged
+}
+
+// Pad with zeros to a whole number of blocks (at least one)
+fn zero_padding(data: &[u8]) -> Vec<u8> {
+    let mut padded = data.to_vec();
+    padded.resize(data.len().div_ceil(BLOCK_SIZE).max(1) * BLOCK_SIZE, 0);
+    padded
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::{aes128::AES128, cbc_mac::{CBCMACMode, CBCMAC}, AES};
+    use super::{forge_extension, naive_cbc_mac};
+
+    const KEY: [u8; 16] = [0x2b, 0x7e, 0x15, 0x16, 0x28, 0xae, 0xd2, 0xa6,
+                            0xab, 0xf7, 0x15, 0x88, 0x09, 0xcf, 0x4f, 0x3c];
+
+    #[test]
+    fn test_naive_forgery() {
+        let aes = AES128::new(&KEY, Some([0; 16])).unwrap();
+        // The attacker only sees these two tagged messages
+        let m1 = *b"pay 100 to alice";
+        let m2 = *b"pay 999 to mallo";
+        let t1 = naive_cbc_mac(aes.as_ref(), &m1).unwrap();
+        let t2 = naive_cbc_mac(aes.as_ref(), &m2).unwrap();
+
+        // ...and produces a new message that verifies with t2
+        let forged = forge_extension(&m1, &t1, &m2);
+        assert_ne!(forged, m2.to_vec());
+        assert_eq!(naive_cbc_mac(aes.as_ref(), &forged).unwrap(), t2);
+    }
+
+    #[test]
+    fn test_naive_zero_padding_collision() {
+        let aes = AES128::new(&KEY, Some([0; 16])).unwrap();
+        // Zero padding makes a message and its padded version indistinguishable
+        assert_eq!(naive_cbc_mac(aes.as_ref(), b"abc").unwrap(),
+                    naive_cbc_mac(aes.as_ref(), b"abc\0\0").unwrap());
+    }
+
+    #[test]
+    fn test_length_prepended_resists_forgery() {
+        let mac = CBCMAC::<AES128>::new(&KEY, CBCMACMode::LengthPrepended).unwrap();
+        let m1 = *b"pay 100 to alice";
+        let m2 = *b"pay 999 to mallo";
+        let t1 = mac.mac(&m1).unwrap();
+        let t2 = mac.mac(&m2).unwrap();
+
+        let forged = forge_extension(&m1, &t1, &m2);
+        assert!(mac.verify(&forged, &t2).is_err());
+    }
+
+    #[test]
+    fn test_fixed_length_rejects_forgery() {
+        let mac = CBCMAC::<AES128>::new(&KEY, CBCMACMode::FixedLength(16)).unwrap();
+        let m1 = *b"pay 100 to alice";
+        let m2 = *b"pay 999 to mallo";
+        let t1 = mac.mac(&m1).unwrap();
+        let t2 = mac.mac(&m2).unwrap();
+
+        // The forged message has two blocks, so it is not even accepted
+        let forged = forge_extension(&m1, &t1, &m2);
+        assert!(mac.verify(&forged, &t2).is_err());
+    }
+}
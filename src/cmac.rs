@@ -0,0 +1,209 @@
+use crate::{aes128::AES128, utils::{add_iv, constant_time_eq, gf128_double}, AESError, AES, BLOCK_SIZE};
+
+// Shortest tag accepted when truncating (64 bits, as recommended by SP 800-38B)
+const MIN_TAG_SIZE: usize = 8;
+
+
+// Derive the subkeys K1 and K2 from L = E_K(0)
+pub(crate) fn subkeys<A: AES>(cipher: &A) -> ([u8; BLOCK_SIZE], [u8; BLOCK_SIZE]) {
+    let l = cipher.encrypt_block(&[0; BLOCK_SIZE]);
+    let k1 = gf128_double(&l);
+    let k2 = gf128_double(&k1);
+
+    (k1, k2)
+}
+
+// Incremental CMAC (also known as OMAC1), RFC 4493 and SP 800-38B
+#[allow(clippy::upper_case_acronyms)]
+pub struct CMAC<'a, A: AES> {
+    cipher: &'a A,
+    k1: [u8; BLOCK_SIZE],
+    k2: [u8; BLOCK_SIZE],
+    state: [u8; BLOCK_SIZE],
+    // Last block is kept until we know whether it is the final one
+    buffer: Vec<u8>,
+}
+
+impl<'a, A: AES> CMAC<'a, A> {
+    pub fn new(cipher: &'a A) -> CMAC<'a, A> {
+        let (k1, k2) = subkeys(cipher);
+
+        CMAC {
+            cipher,
+            k1,
+            k2,
+            state: [0; BLOCK_SIZE],
+            buffer: Vec::with_capacity(BLOCK_SIZE),
+        }
+    }
+
+    pub fn update(&mut self, mut data: &[u8]) {
+        while !data.is_empty() {
+            // More data is coming, so a full buffer is not the last block
+            if self.buffer.len() == BLOCK_SIZE {
+                let block: [u8; BLOCK_SIZE] = self.buffer.as_slice().try_into().unwrap();
+                self.state = self.cipher.encrypt_block(&add_iv(&self.state, &block));
+                self.buffer.clear();
+            }
+
+            let taken = data.len().min(BLOCK_SIZE - self.buffer.len());
+            self.buffer.extend_from_slice(&data[..taken]);
+            data = &data[taken..];
+        }
+    }
+
+    // Last block is masked with K1 if complete, otherwise padded with 10..0 and masked with K2
+    pub fn finalize(self) -> [u8; BLOCK_SIZE] {
+        let mut last = [0; BLOCK_SIZE];
+        last[..self.buffer.len()].copy_from_slice(&self.buffer);
+        let last = if self.buffer.len() == BLOCK_SIZE {
+            add_iv(&last, &self.k1)
+        } else {
+            last[self.buffer.len()] = 0x80;
+            add_iv(&last, &self.k2)
+        };
+
+        self.cipher.encrypt_block(&add_iv(&self.state, &last))
+    }
+
+    // Keep only the first tag_size bytes of the tag
+    pub fn finalize_truncated(self, tag_size: usize) -> Result<Vec<u8>, AESError> {
+        if !(MIN_TAG_SIZE..=BLOCK_SIZE).contains(&tag_size) {
+            return Err(AESError::WrongTagSize(tag_size, BLOCK_SIZE));
+        }
+
+        Ok(self.finalize()[..tag_size].to_vec())
+    }
+
+    // Check a full or truncated tag in constant time
+    pub fn verify(self, tag: &[u8]) -> Result<(), AESError> {
+        let expected = self.finalize_truncated(tag.len())?;
+        if !constant_time_eq(&expected, tag) {
+            return Err(AESError::AuthenticationFailed);
+        }
+
+        Ok(())
+    }
+}
+
+// Compute CMAC of the data in one call
+pub fn cmac<A: AES>(cipher: &A, data: &[u8]) -> [u8; BLOCK_SIZE] {
+    let mut mac = CMAC::new(cipher);
+    mac.update(data);
+    mac.finalize()
+}
+
+// AES-CMAC-PRF-128 (RFC 4615), the key can have any length
+pub fn cmac_prf_128(key: &[u8], data: &[u8]) -> [u8; BLOCK_SIZE] {
+    let key = if key.len() == BLOCK_SIZE {
+        key.try_into().unwrap()
+    } else {
+        // Other lengths are first compressed with the all-zero key
+        cmac(AES128::new(&[0; BLOCK_SIZE], None).unwrap().as_ref(), key)
+    };
+
+    cmac(AES128::new(&key, None).unwrap().as_ref(), data)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::{aes128::AES128, utils::decode, AESError, AES};
+    use super::{cmac, cmac_prf_128, subkeys, CMAC};
+
+    const KEY: &str = "2b7e151628aed2a6abf7158809cf4f3c";
+    const MESSAGE: &str = "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+                            30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710";
+
+    // RFC 4493 examples, the message is a prefix of MESSAGE
+    const EXAMPLES: [(usize, &str); 4] = [
+        (0, "bb1d6929e95937287fa37d129b756746"),
+        (16, "070a16b46b4d4144f79bdd9dd04a287c"),
+        (40, "dfa66747de9ae63030ca32611497c827"),
+        (64, "51f0bebf7e3b9d92fc49741779363cfe"),
+    ];
+
+    #[test]
+    fn test_subkeys() {
+        let aes = AES128::new(&decode(KEY).unwrap(), None).unwrap();
+        let (k1, k2) = subkeys(aes.as_ref());
+
+        assert_eq!(k1.to_vec(), decode("fbeed618357133667c85e08f7236a8de").unwrap());
+        assert_eq!(k2.to_vec(), decode("f7ddac306ae266ccf90bc11ee46d513b").unwrap());
+    }
+
+    #[test]
+    fn test_cmac_examples() {
+        let aes = AES128::new(&decode(KEY).unwrap(), None).unwrap();
+        let message = decode(MESSAGE).unwrap();
+
+        for (len, expected) in EXAMPLES {
+            let tag = cmac(aes.as_ref(), &message[..len]);
+            assert_eq!(tag.to_vec(), decode(expected).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_cmac_incremental() {
+        let aes = AES128::new(&decode(KEY).unwrap(), None).unwrap();
+        let message = decode(MESSAGE).unwrap();
+
+        for (len, expected) in EXAMPLES {
+            for chunk_size in [1, 7, 16, 17] {
+                let mut mac = CMAC::new(aes.as_ref());
+                for chunk in message[..len].chunks(chunk_size) {
+                    mac.update(chunk);
+                }
+                assert_eq!(mac.finalize().to_vec(), decode(expected).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn test_cmac_verify() {
+        let aes = AES128::new(&decode(KEY).unwrap(), None).unwrap();
+        let message = decode(MESSAGE).unwrap();
+        let tag = decode("dfa66747de9ae63030ca32611497c827").unwrap();
+
+        let mut mac = CMAC::new(aes.as_ref());
+        mac.update(&message[..40]);
+        assert!(mac.verify(&tag).is_ok());
+
+        let mut mac = CMAC::new(aes.as_ref());
+        mac.update(&message[..39]);
+        assert!(matches!(mac.verify(&tag), Err(AESError::AuthenticationFailed)));
+    }
+
+    #[test]
+    fn test_cmac_truncated() {
+        let aes = AES128::new(&decode(KEY).unwrap(), None).unwrap();
+        let message = decode(MESSAGE).unwrap();
+
+        let mut mac = CMAC::new(aes.as_ref());
+        mac.update(&message);
+        let tag = mac.finalize_truncated(8).unwrap();
+        assert_eq!(tag, decode("51f0bebf7e3b9d92").unwrap());
+
+        let mut mac = CMAC::new(aes.as_ref());
+        mac.update(&message);
+        assert!(mac.verify(&tag).is_ok());
+
+        let mac = CMAC::new(aes.as_ref());
+        assert!(matches!(mac.verify(&tag[..4]), Err(AESError::WrongTagSize(4, 16))));
+    }
+
+    // RFC 4615, section 4: keys of 18, 16 and 10 bytes
+    #[test]
+    fn test_cmac_prf_128() {
+        let message = decode("000102030405060708090a0b0c0d0e0f10111213").unwrap();
+        let examples = [
+            ("000102030405060708090a0b0c0d0e0fedcb", "84a348a4a45d235babfffc0d2b4da09a"),
+            ("000102030405060708090a0b0c0d0e0f", "980ae87b5f4c9c5214f5b6a8455e4c2d"),
+            ("00010203040506070809", "290d9e112edb09ee141fcf64c0b72f3d"),
+        ];
+
+        for (key, expected) in examples {
+            assert_eq!(cmac_prf_128(&decode(key).unwrap(), &message).to_vec(), decode(expected).unwrap());
+        }
+    }
+}
@@ -0,0 +1,377 @@
+use std::{fs::File, io::Read};
+use crate::{aes128::AES128, utils::{add_iv, increment_counter}, AESError, AES, BLOCK_SIZE};
+
+const KEY_SIZE: usize = 16;
+// seedlen = keylen + outlen
+const SEED_SIZE: usize = KEY_SIZE + BLOCK_SIZE;
+// Security strength of AES-128, minimum entropy when the derivation function is used
+const SECURITY_STRENGTH: usize = 16;
+// Maximum number of generate calls between two reseeds (SP 800-90A, Table 3)
+const MAX_RESEED_INTERVAL: u64 = 1 << 48;
+// Maximum output of one generate call, 2^19 bits
+const MAX_REQUEST_SIZE: usize = 1 << 16;
+const ENTROPY_SOURCE: &str = "/dev/urandom";
+
+
+// Read fresh entropy from the operating system
+fn os_entropy(size: usize) -> Result<Vec<u8>, AESError> {
+    let mut entropy = vec![0; size];
+    File::open(ENTROPY_SOURCE)
+        .and_then(|mut f| f.read_exact(&mut entropy))
+        .map_err(|e| AESError::EntropySourceFailed(e.to_string()))?;
+
+    Ok(entropy)
+}
+
+// CBC-MAC with a zero IV, used by the derivation function
+fn bcc(cipher: &AES128, data: &[u8]) -> [u8; BLOCK_SIZE] {
+    data.chunks_exact(BLOCK_SIZE).fold([0; BLOCK_SIZE], |chaining, block| {
+        cipher.encrypt_block(&add_iv(&chaining, block.try_into().unwrap()))
+    })
+}
+
+// Block_Cipher_df, always returns seedlen bytes
+fn derivation_function(input: &[u8]) -> [u8; SEED_SIZE] {
+    // S = L || N || input || 0x80 || 0..0
+    let mut s = (input.len() as u32).to_be_bytes().to_vec();
+    s.extend((SEED_SIZE as u32).to_be_bytes());
+    s.extend_from_slice(input);
+    s.push(0x80);
+    s.resize(s.len().div_ceil(BLOCK_SIZE) * BLOCK_SIZE, 0);
+
+    let key: Vec<u8> = (0..KEY_SIZE as u8).collect();
+    let cipher = AES128::new(&key, None).unwrap();
+    let mut temp = Vec::with_capacity(SEED_SIZE);
+    let mut i: u32 = 0;
+    while temp.len() < SEED_SIZE {
+        let mut data = [0; BLOCK_SIZE].to_vec();
+        data[..4].copy_from_slice(&i.to_be_bytes());
+        data.extend_from_slice(&s);
+        temp.extend(bcc(&cipher, &data));
+        i += 1;
+    }
+
+    let cipher = AES128::new(&temp[..KEY_SIZE], None).unwrap();
+    let mut x: [u8; BLOCK_SIZE] = temp[KEY_SIZE..SEED_SIZE].try_into().unwrap();
+    let mut result = [0; SEED_SIZE];
+    for chunk in result.chunks_exact_mut(BLOCK_SIZE) {
+        x = cipher.encrypt_block(&x);
+        chunk.copy_from_slice(&x);
+    }
+
+    result
+}
+
+// CTR_DRBG (SP 800-90A) with AES-128, with or without the derivation function
+#[allow(clippy::upper_case_acronyms)]
+pub struct CTRDRBG {
+    cipher: Box<AES128>,
+    v: [u8; BLOCK_SIZE],
+    reseed_counter: u64,
+    reseed_interval: u64,
+    derivation: bool,
+    prediction_resistance: bool,
+}
+
+impl CTRDRBG {
+    // Instantiate from the OS entropy source
+    pub fn new(personalization: &[u8], derivation: bool, prediction_resistance: bool) -> Result<CTRDRBG, AESError> {
+        // Without the derivation function the entropy is used as full seed
+        let entropy = os_entropy(if derivation { SECURITY_STRENGTH } else { SEED_SIZE })?;
+        let nonce = if derivation { os_entropy(SECURITY_STRENGTH / 2)? } else { Vec::new() };
+
+        Self::instantiate(&entropy, &nonce, personalization, derivation, prediction_resistance)
+    }
+
+    // Deterministic instantiation with the given inputs (the nonce is ignored without derivation function)
+    pub fn instantiate(entropy: &[u8], nonce: &[u8], personalization: &[u8], derivation: bool, prediction_resistance: bool)
+                        -> Result<CTRDRBG, AESError> {
+        let seed = if derivation {
+            if entropy.len() < SECURITY_STRENGTH {
+                return Err(AESError::WrongSeedLength(entropy.len(), SECURITY_STRENGTH));
+            }
+            derivation_function(&[entropy, nonce, personalization].concat())
+        } else {
+            Self::xor_seed(entropy, personalization)?
+        };
+
+        let mut drbg = CTRDRBG {
+            cipher: AES128::new(&[0; KEY_SIZE], None)?,
+            v: [0; BLOCK_SIZE],
+            reseed_counter: 1,
+            reseed_interval: MAX_RESEED_INTERVAL,
+            derivation,
+            prediction_resistance,
+        };
+        drbg.update(&seed);
+
+        Ok(drbg)
+    }
+
+    // Without derivation function: entropy (exactly seedlen) xor the padded input
+    fn xor_seed(entropy: &[u8], input: &[u8]) -> Result<[u8; SEED_SIZE], AESError> {
+        if entropy.len() != SEED_SIZE {
+            return Err(AESError::WrongSeedLength(entropy.len(), SEED_SIZE));
+        }
+        if input.len() > SEED_SIZE {
+            return Err(AESError::WrongSeedLength(input.len(), SEED_SIZE));
+        }
+
+        let mut seed: [u8; SEED_SIZE] = entropy.try_into().unwrap();
+        for (s, b) in seed.iter_mut().zip(input) {
+            *s ^= b;
+        }
+        Ok(seed)
+    }
+
+    // Additional input as seedlen bytes (zeros when empty)
+    fn additional_seed(&self, additional: &[u8]) -> Result<[u8; SEED_SIZE], AESError> {
+        if additional.is_empty() {
+            Ok([0; SEED_SIZE])
+        } else if self.derivation {
+            Ok(derivation_function(additional))
+        } else {
+            Self::xor_seed(&[0; SEED_SIZE], additional)
+        }
+    }
+
+    // CTR_DRBG_Update: Key || V = (E_K(V+1) || E_K(V+2)) ^ provided
+    fn update(&mut self, provided: &[u8; SEED_SIZE]) {
+        let mut temp = [0; SEED_SIZE];
+        for chunk in temp.chunks_exact_mut(BLOCK_SIZE) {
+            increment_counter(&mut self.v);
+            chunk.copy_from_slice(&self.cipher.encrypt_block(&self.v));
+        }
+        for (t, p) in temp.iter_mut().zip(provided) {
+            *t ^= p;
+        }
+
+        self.cipher = AES128::new(&temp[..KEY_SIZE], None).unwrap();
+        self.v = temp[KEY_SIZE..].try_into().unwrap();
+    }
+
+    // Reseed with the given entropy
+    pub fn reseed_with(&mut self, entropy: &[u8], additional: &[u8]) -> Result<(), AESError> {
+        let seed = if self.derivation {
+            if entropy.len() < SECURITY_STRENGTH {
+                return Err(AESError::WrongSeedLength(entropy.len(), SECURITY_STRENGTH));
+            }
+            derivation_function(&[entropy, additional].concat())
+        } else {
+            Self::xor_seed(entropy, additional)?
+        };
+        self.update(&seed);
+        self.reseed_counter = 1;
+
+        Ok(())
+    }
+
+    // Reseed from the OS entropy source
+    pub fn reseed(&mut self, additional: &[u8]) -> Result<(), AESError> {
+        let entropy = os_entropy(if self.derivation { SECURITY_STRENGTH } else { SEED_SIZE })?;
+        self.reseed_with(&entropy, additional)
+    }
+
+    // Fill the output with random bytes, reseeding first when prediction resistance
+    // is enabled or when the reseed counter reached its limit
+    pub fn generate(&mut self, output: &mut [u8], additional: &[u8]) -> Result<(), AESError> {
+        if self.prediction_resistance || self.reseed_counter > self.reseed_interval {
+            let entropy = os_entropy(if self.derivation { SECURITY_STRENGTH } else { SEED_SIZE })?;
+            return self.generate_with_entropy(output, &entropy, additional);
+        }
+        self.generate_without_reseed(output, additional)
+    }
+
+    // Reseed with the given entropy and additional input, then generate
+    // (the prediction resistance path, EntropyInputPR in the CAVP tests)
+    pub fn generate_with_entropy(&mut self, output: &mut [u8], entropy: &[u8], additional: &[u8]) -> Result<(), AESError> {
+        self.reseed_with(entropy, additional)?;
+        self.generate_without_reseed(output, &[])
+    }
+
+    // Generate without ever touching the entropy source (used for known-answer tests)
+    pub fn generate_without_reseed(&mut self, output: &mut [u8], additional: &[u8]) -> Result<(), AESError> {
+        if output.len() > MAX_REQUEST_SIZE {
+            return Err(AESError::RequestTooLarge(output.len(), MAX_REQUEST_SIZE));
+        }
+        if self.reseed_counter > self.reseed_interval {
+            return Err(AESError::ReseedRequired);
+        }
+
+        // The update depends on whether additional input was given, not on its value
+        let provided = !additional.is_empty();
+        let additional = self.additional_seed(additional)?;
+        if provided {
+            self.update(&additional);
+        }
+
+        for chunk in output.chunks_mut(BLOCK_SIZE) {
+            increment_counter(&mut self.v);
+            let block = self.cipher.encrypt_block(&self.v);
+            chunk.copy_from_slice(&block[..chunk.len()]);
+        }
+
+        self.update(&additional);
+        self.reseed_counter += 1;
+
+        Ok(())
+    }
+
+    // Lower the number of generate calls allowed between reseeds
+    pub fn set_reseed_interval(&mut self, interval: u64) {
+        self.reseed_interval = interval.clamp(1, MAX_RESEED_INTERVAL);
+    }
+
+    pub fn reseed_counter(&self) -> u64 {
+        self.reseed_counter
+    }
+
+    // Convenience for fresh keys and IVs
+    pub fn random_block(&mut self) -> Result<[u8; BLOCK_SIZE], AESError> {
+        let mut block = [0; BLOCK_SIZE];
+        self.generate(&mut block, &[])?;
+        Ok(block)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::{utils::decode, AESError};
+    use super::CTRDRBG;
+
+    // CAVP CTR_DRBG (drbgvectors_no_reseed), AES-128, PredictionResistance = False,
+    // no personalization and no additional input, first entry of each section:
+    // generate is called twice and ReturnedBits is the second output
+    #[test]
+    fn test_ctr_drbg_no_df() {
+        let entropy = decode("ce50f33da5d4c1d3d4004eb35244b7f2cd7f2e5076fbf6780a7ff634b249a5fc").unwrap();
+        let mut drbg = CTRDRBG::instantiate(&entropy, &[], &[], false, false).unwrap();
+
+        let mut output = [0; 64];
+        drbg.generate_without_reseed(&mut output, &[]).unwrap();
+        drbg.generate_without_reseed(&mut output, &[]).unwrap();
+        assert_eq!(output.to_vec(), decode("6545c0529d372443b392ceb3ae3a99a30f963eaf313280f1d1a1e87f9db373d3\
+                                            61e75d18018266499cccd64d9bbb8de0185f213383080faddec46bae1f784e5a").unwrap());
+    }
+
+    #[test]
+    fn test_ctr_drbg_df() {
+        let entropy = decode("890eb067acf7382eff80b0c73bc872c6").unwrap();
+        let nonce = decode("aad471ef3ef1d203").unwrap();
+        let mut drbg = CTRDRBG::instantiate(&entropy, &nonce, &[], true, false).unwrap();
+
+        let mut output = [0; 64];
+        drbg.generate_without_reseed(&mut output, &[]).unwrap();
+        drbg.generate_without_reseed(&mut output, &[]).unwrap();
+        assert_eq!(output.to_vec(), decode("a5514ed7095f64f3d0d3a5760394ab42062f373a25072a6ea6bcfd8489e94af6\
+                                            cf18659fea22ed1ca0a9e33f718b115ee536b12809c31b72b08ddd8be1910fa3").unwrap());
+    }
+
+    // Bytes start, start + 7, start + 14, ...
+    fn pattern(size: usize, start: u8) -> Vec<u8> {
+        (0..size).map(|i| start.wrapping_add((7 * i) as u8)).collect()
+    }
+
+    // Personalization, additional input, reseed and prediction resistance. No CAVP vector for these
+    // is at hand: the outputs come from an independent model of SP 800-90A on OpenSSL's AES, which
+    // reproduces the two CAVP entries above. As in CAVP, ReturnedBits is the second output
+    #[test]
+    fn test_ctr_drbg_known_answers() {
+        let vectors = [
+            (true,
+             "486d3964421a048c07f6b4d902e33536566baa89a213a559e19790a77e4dc2d1bbfeee365a784f234541600013272df2e94a421d2f78023f2d361c41343435a3",
+             "645d9db6af0c91802b88dc93d26540aab450c43f05f9b27102c1b27650acf6f67a5ccc7c8846a091241e656e3c9ec9f4caaecf51a2f8b3bec9b705219bed8c0d",
+             "d0c9a169630095871db91732ceabb91853431653b27f87a720ce57ecaceff3724f185e4ca96bffa0ff69f0d381913b325a1d7e3f1691af8627df65251907fe83"),
+            (false,
+             "fb7b2f0cba384c841b2f5651135f99fafa48354009530bf2d1e0bd47442771ee14fbe6deb670cf87b209b64cdf7c653156eaeef4f8c8ba7ec05131f9d94a55f6",
+             "c597b08e551f61d2b8f611bedc5e9fa3fa4d0c360b57bae93abab3d15b522cfdd35feaa4cffe350681c06fcf72f7dbd7aa415f574eba6d9eb060209c3e78a5fe",
+             "e447b7e0967810e2b6ab73451fdf3698d14438ba6d5d1764e2278a844b8ef67a126700bd1c1eb3932df386e7d93db4dfac85c1b9b37503abe36a6c2f7170f1b9"),
+        ];
+        for (derivation, additional_bits, reseed_bits, prediction_bits) in vectors {
+            // Inputs as long as the seed without derivation function, security strength otherwise
+            let size = if derivation { 16 } else { 32 };
+            let nonce = if derivation { pattern(8, 0x20) } else { Vec::new() };
+            let (entropy, personalization) = (pattern(size, 0x10), pattern(size, 0x30));
+            let (additional1, additional2) = (pattern(size, 0x40), pattern(size, 0x50));
+            let mut output = [0; 64];
+
+            let mut drbg = CTRDRBG::instantiate(&entropy, &nonce, &personalization, derivation, false).unwrap();
+            drbg.generate_without_reseed(&mut output, &additional1).unwrap();
+            drbg.generate_without_reseed(&mut output, &additional2).unwrap();
+            assert_eq!(output.to_vec(), decode(additional_bits).unwrap());
+
+            let mut drbg = CTRDRBG::instantiate(&entropy, &nonce, &personalization, derivation, false).unwrap();
+            drbg.reseed_with(&pattern(size, 0x60), &pattern(size, 0x70)).unwrap();
+            drbg.generate_without_reseed(&mut output, &additional1).unwrap();
+            drbg.generate_without_reseed(&mut output, &additional2).unwrap();
+            assert_eq!(output.to_vec(), decode(reseed_bits).unwrap());
+
+            let mut drbg = CTRDRBG::instantiate(&entropy, &nonce, &personalization, derivation, true).unwrap();
+            drbg.generate_with_entropy(&mut output, &pattern(size, 0x80), &additional1).unwrap();
+            drbg.generate_with_entropy(&mut output, &pattern(size, 0x90), &additional2).unwrap();
+            assert_eq!(output.to_vec(), decode(prediction_bits).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_ctr_drbg_reseed_interval() {
+        let mut drbg = CTRDRBG::instantiate(&[0x42; 32], &[], b"personalization", false, false).unwrap();
+        drbg.set_reseed_interval(2);
+
+        let mut output = [0; 16];
+        drbg.generate(&mut output, &[]).unwrap();
+        drbg.generate(&mut output, &[]).unwrap();
+        assert_eq!(drbg.reseed_counter(), 3);
+        assert!(matches!(drbg.generate_without_reseed(&mut output, &[]), Err(AESError::ReseedRequired)));
+
+        // The limit is reached, generate reseeds from the OS first
+        drbg.generate(&mut output, b"additional").unwrap();
+        assert_eq!(drbg.reseed_counter(), 2);
+    }
+
+    #[test]
+    fn test_ctr_drbg_prediction_resistance() {
+        let mut drbg = CTRDRBG::new(b"nonce generator", true, true).unwrap();
+
+        let first = drbg.random_block().unwrap();
+        let second = drbg.random_block().unwrap();
+        assert_ne!(first, second);
+        // Every call reseeded
+        assert_eq!(drbg.reseed_counter(), 2);
+    }
+
+    #[test]
+    fn test_ctr_drbg_reseed() {
+        // Same inputs give the same output, a reseed changes it
+        let mut a = CTRDRBG::instantiate(&[0x42; 16], &[0x01; 8], &[], true, false).unwrap();
+        let mut b = CTRDRBG::instantiate(&[0x42; 16], &[0x01; 8], &[], true, false).unwrap();
+        let (mut x, mut y) = ([0; 40], [0; 40]);
+        a.generate_without_reseed(&mut x, b"additional").unwrap();
+        b.generate_without_reseed(&mut y, b"additional").unwrap();
+        assert_eq!(x, y);
+
+        // All-zero additional input still counts as provided
+        let mut c = CTRDRBG::instantiate(&[0x42; 32], &[], &[], false, false).unwrap();
+        let mut d = CTRDRBG::instantiate(&[0x42; 32], &[], &[], false, false).unwrap();
+        c.generate_without_reseed(&mut x, &[0; 32]).unwrap();
+        d.generate_without_reseed(&mut y, &[]).unwrap();
+        assert_ne!(x, y);
+
+        b.reseed_with(&[0x43; 16], &[]).unwrap();
+        a.generate_without_reseed(&mut x, &[]).unwrap();
+        b.generate_without_reseed(&mut y, &[]).unwrap();
+        assert_ne!(x, y);
+    }
+
+    #[test]
+    fn test_ctr_drbg_errors() {
+        assert!(matches!(CTRDRBG::instantiate(&[0; 16], &[], &[], false, false), Err(AESError::WrongSeedLength(16, 32))));
+        assert!(matches!(CTRDRBG::instantiate(&[0; 8], &[0; 8], &[], true, false), Err(AESError::WrongSeedLength(8, 16))));
+        assert!(matches!(CTRDRBG::instantiate(&[0; 32], &[], &[0; 33], false, false), Err(AESError::WrongSeedLength(33, 32))));
+
+        let mut drbg = CTRDRBG::new(&[], false, false).unwrap();
+        let mut output = vec![0; (1 << 16) + 1];
+        assert!(matches!(drbg.generate(&mut output, &[]), Err(AESError::RequestTooLarge(65537, 65536))));
+    }
+}
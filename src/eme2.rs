@@ -0,0 +1,237 @@
+use crate::{utils::{add_iv, gf128_double_le}, AESError, AES, BLOCK_SIZE};
+
+
+// Maximum number of blocks mixed with the same M before it is refreshed
+const BLOCKS_PER_MIX: usize = 128;
+
+// EME2 (IEEE 1619.2): encrypt-mix-encrypt wide-block mode, the whole sector
+// behaves as one big block so a single changed bit garbles all of it
+pub struct EME2<A: AES> {
+    cipher: Box<A>,
+    // Mask for the associated data (tweak)
+    key_ad: [u8; BLOCK_SIZE],
+    // Mask for the two ECB layers
+    key_ecb: [u8; BLOCK_SIZE],
+}
+
+impl<A: AES> EME2<A> {
+    // The key is K_AD || K_ECB || K_AES (16 + 16 + 16/24/32 bytes)
+    pub fn new(key: &[u8]) -> Result<EME2<A>, AESError> {
+        // The shortest AES key is one block, A::new checks the exact size of K_AES
+        if key.len() < 3*BLOCK_SIZE {
+            return Err(AESError::WrongKeySize(key.len(), 3*BLOCK_SIZE));
+        }
+        let (key_ad, rest) = key.split_at(BLOCK_SIZE);
+        let (key_ecb, key_aes) = rest.split_at(BLOCK_SIZE);
+
+        Ok(EME2 {
+            cipher: A::new(key_aes, None)?,
+            key_ad: key_ad.try_into().unwrap(),
+            key_ecb: key_ecb.try_into().unwrap(),
+        })
+    }
+
+    // Pad a partial block with 10..0
+    fn pad(data: &[u8]) -> [u8; BLOCK_SIZE] {
+        let mut block = [0; BLOCK_SIZE];
+        block[..data.len()].copy_from_slice(data);
+        block[data.len()] = 0x80;
+        block
+    }
+
+    // T* = XOR of E(T_i ^ K_T) ^ K_T, with K_T doubled after each block
+    fn tweak_digest(&self, tweak: &[u8]) -> [u8; BLOCK_SIZE] {
+        if tweak.is_empty() {
+            return self.cipher.encrypt_block(&self.key_ad);
+        }
+
+        let mut digest = [0; BLOCK_SIZE];
+        let mut mask = self.key_ad;
+        let mut chunks = tweak.chunks(BLOCK_SIZE).peekable();
+        while let Some(chunk) = chunks.next() {
+            let block = if chunk.len() == BLOCK_SIZE {
+                chunk.try_into().unwrap()
+            } else {
+                // An incomplete last block uses one more doubling
+                mask = gf128_double_le(&mask);
+                Self::pad(chunk)
+            };
+            let encrypted = add_iv(&self.cipher.encrypt_block(&add_iv(&block, &mask)), &mask);
+            digest = add_iv(&digest, &encrypted);
+
+            if chunks.peek().is_some() {
+                mask = gf128_double_le(&mask);
+            }
+        }
+
+        digest
+    }
+
+    // Encryption and decryption have exactly the same structure, only the
+    // block function changes (E for encryption, D for decryption)
+    fn process(&self, tweak: &[u8], data: &[u8], f: &dyn Fn(&[u8; BLOCK_SIZE]) -> [u8; BLOCK_SIZE]) -> Result<Vec<u8>, AESError> {
+        if data.len() < BLOCK_SIZE {
+            return Err(AESError::DataTooShort(data.len(), BLOCK_SIZE));
+        }
+        let full_blocks = data.len() / BLOCK_SIZE;
+        let rest = &data[full_blocks*BLOCK_SIZE..];
+        let t_star = self.tweak_digest(tweak);
+
+        // First ECB layer: PPP_i = f(P_i ^ L), L doubled for every block
+        let mut masks = Vec::with_capacity(full_blocks);
+        let mut mask = self.key_ecb;
+        let mut blocks: Vec<[u8; BLOCK_SIZE]> = Vec::with_capacity(full_blocks + 1);
+        for chunk in data.chunks_exact(BLOCK_SIZE) {
+            let block: [u8; BLOCK_SIZE] = chunk.try_into().unwrap();
+            blocks.push(f(&add_iv(&block, &mask)));
+            masks.push(mask);
+            mask = gf128_double_le(&mask);
+        }
+        if !rest.is_empty() {
+            blocks.push(Self::pad(rest));
+        }
+
+        // Mixing layer
+        let mp = blocks.iter().fold(t_star, |acc, block| add_iv(&acc, block));
+        let mut last = Vec::new();
+        let mc = if rest.is_empty() {
+            f(&mp)
+        } else {
+            let mm = f(&mp);
+            last = rest.iter().zip(mm.iter()).map(|(a, b)| a ^ b).collect();
+            f(&mm)
+        };
+        let m1 = add_iv(&mp, &mc);
+
+        let mut m = m1;
+        for (i, block) in blocks.iter_mut().enumerate().take(full_blocks).skip(1) {
+            if i % BLOCKS_PER_MIX == 0 {
+                let mp = add_iv(block, &m1);
+                let mc = f(&mp);
+                m = add_iv(&mp, &mc);
+                *block = add_iv(&mc, &m1);
+            } else {
+                m = gf128_double_le(&m);
+                *block = add_iv(block, &m);
+            }
+        }
+        if !rest.is_empty() {
+            blocks[full_blocks] = Self::pad(&last);
+        }
+        blocks[0] = blocks[1..].iter().fold(add_iv(&mc, &t_star), |acc, block| add_iv(&acc, block));
+
+        // Second ECB layer: C_i = f(CCC_i) ^ L
+        let mut result = Vec::with_capacity(data.len());
+        for (block, mask) in blocks.iter().zip(masks.iter()) {
+            result.extend(add_iv(&f(block), mask));
+        }
+        result.extend(last);
+
+        Ok(result)
+    }
+
+    // The data must be at least one block, the tweak (associated data) can have any length
+    pub fn encrypt(&self, tweak: &[u8], data: &[u8]) -> Result<Vec<u8>, AESError> {
+        self.process(tweak, data, &|block| self.cipher.encrypt_block(block))
+    }
+
+    pub fn decrypt(&self, tweak: &[u8], data: &[u8]) -> Result<Vec<u8>, AESError> {
+        self.process(tweak, data, &|block| self.cipher.decrypt_block(block))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::{aes128::AES128, sha256::sha256, utils::decode, AESError};
+    use super::EME2;
+
+    fn key() -> Vec<u8> {
+        (0..48).collect()
+    }
+
+    #[test]
+    fn test_eme2_known_answers() {
+        // No IEEE 1619.2 vector is at hand: these come from an independent model of the
+        // spec on OpenSSL's AES, they pin the doubling and mixing order
+        let eme2 = EME2::<AES128>::new(&key()).unwrap();
+        let vectors = [
+            (&b"sector 42"[..], (0..40).collect::<Vec<u8>>(),
+                "1d4db98f19f4467996a34c2435200a1f613122d7d912d2db3588ad7fb61ec1f476a195284a4017e4"),
+            (&b""[..], (0..32).collect(), "96b0e3f910e193513c9c4ea30a6b4668729f23a997630421eceec6862865b218"),
+        ];
+        for (tweak, data, expected) in vectors {
+            let encrypted = eme2.encrypt(tweak, &data).unwrap();
+            assert_eq!(encrypted, decode(expected).unwrap());
+            assert_eq!(eme2.decrypt(tweak, &encrypted).unwrap(), data);
+        }
+
+        // A whole 4096-byte sector goes through the refresh of M every 128 blocks
+        let data: Vec<u8> = (0..4096).map(|i| (i * 7) as u8).collect();
+        let encrypted = eme2.encrypt(&42u64.to_le_bytes(), &data).unwrap();
+        assert_eq!(
+            sha256(&encrypted).to_vec(),
+            decode("295880ace24b018e8151523beafda89e1684b3b5ae3a5e2e68f2c9fcbf631a83").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_eme2_sectors() {
+        let eme2 = EME2::<AES128>::new(&key()).unwrap();
+        for sector_size in [512, 4096] {
+            let data: Vec<u8> = (0..sector_size).map(|i| (i * 7) as u8).collect();
+            let tweak = 42u64.to_le_bytes();
+            let encrypted = eme2.encrypt(&tweak, &data).unwrap();
+
+            assert_eq!(encrypted.len(), sector_size);
+            assert_ne!(encrypted, data);
+            assert_eq!(eme2.decrypt(&tweak, &encrypted).unwrap(), data);
+
+            // Another sector number gives a completely different ciphertext
+            let other = eme2.encrypt(&43u64.to_le_bytes(), &data).unwrap();
+            assert!(changed_bytes(&encrypted, &other) > sector_size * 9 / 10);
+        }
+    }
+
+    #[test]
+    fn test_eme2_partial_block() {
+        let eme2 = EME2::<AES128>::new(&key()).unwrap();
+        for len in [16, 17, 31, 33, 100] {
+            let data = vec![0x5a; len];
+            for tweak in [&b""[..], b"sector", b"associated data longer than a block"] {
+                let encrypted = eme2.encrypt(tweak, &data).unwrap();
+                assert_eq!(encrypted.len(), len);
+                assert_eq!(eme2.decrypt(tweak, &encrypted).unwrap(), data);
+            }
+        }
+    }
+
+    // Count the bytes that differ between the two buffers
+    fn changed_bytes(a: &[u8], b: &[u8]) -> usize {
+        a.iter().zip(b).filter(|(x, y)| x != y).count()
+    }
+
+    #[test]
+    fn test_eme2_tampering() {
+        let eme2 = EME2::<AES128>::new(&key()).unwrap();
+        for sector_size in [512, 4096] {
+            let data = vec![0; sector_size];
+            let encrypted = eme2.encrypt(b"", &data).unwrap();
+
+            // A flipped bit anywhere in the ciphertext garbles the whole decrypted sector
+            for position in [0, 17, sector_size / 2, sector_size - 1] {
+                let mut tampered = encrypted.clone();
+                tampered[position] ^= 0x01;
+                let decrypted = eme2.decrypt(b"", &tampered).unwrap();
+                assert!(changed_bytes(&decrypted, &data) > sector_size * 9 / 10);
+            }
+        }
+    }
+
+    #[test]
+    fn test_eme2_errors() {
+        assert!(matches!(EME2::<AES128>::new(&[0; 32]), Err(AESError::WrongKeySize(32, 48))));
+        let eme2 = EME2::<AES128>::new(&key()).unwrap();
+        assert!(matches!(eme2.encrypt(b"", &[0; 15]), Err(AESError::DataTooShort(15, 16))));
+    }
+}
@@ -0,0 +1,190 @@
+use std::cell::RefCell;
+use crate::{aes128::AES128, cmac::cmac, ctr_drbg::CTRDRBG, hmac::hmac_sha256,
+    kbkdf::{KBKDFMode, KBKDF}, utils::{constant_time_eq, padding, split_in_blocks, unite_blocks, unpadding},
+    AESError, AESMode, AES, BLOCK_SIZE};
+
+const KEY_SIZE: usize = 16;
+const KDF_CONTEXT: &[u8] = b"encrypt-then-mac";
+const IV_PERSONALIZATION: &[u8] = b"encrypt-then-mac iv";
+
+
+// MAC used to authenticate IV || ciphertext
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum MACAlgorithm {
+    CMAC,
+    HMACSHA256,
+}
+
+impl MACAlgorithm {
+    fn key_size(&self) -> usize {
+        match self {
+            MACAlgorithm::CMAC => KEY_SIZE,
+            MACAlgorithm::HMACSHA256 => 32,
+        }
+    }
+
+    fn tag_size(&self) -> usize {
+        match self {
+            MACAlgorithm::CMAC => BLOCK_SIZE,
+            MACAlgorithm::HMACSHA256 => 32,
+        }
+    }
+}
+
+// Encrypt-then-MAC over any unauthenticated mode: the output is IV || C || tag,
+// the tag is checked before anything is decrypted
+pub struct EncryptThenMAC {
+    mode: AESMode,
+    mac: MACAlgorithm,
+    encryption_key: Vec<u8>,
+    mac_key: Vec<u8>,
+    // Seeded once from the OS, generating IVs needs mutable access
+    drbg: RefCell<CTRDRBG>,
+}
+
+impl EncryptThenMAC {
+    // Independent encryption and MAC keys are derived from the master key (KBKDF in counter mode)
+    pub fn new(master_key: &[u8], mode: AESMode, mac: MACAlgorithm) -> Result<EncryptThenMAC, AESError> {
+        let kdf = KBKDF::<AES128>::new(master_key, KBKDFMode::Counter, 32)?;
+
+        Ok(EncryptThenMAC {
+            mode,
+            mac,
+            encryption_key: kdf.derive(b"encryption", KDF_CONTEXT, KEY_SIZE)?,
+            mac_key: kdf.derive(b"authentication", KDF_CONTEXT, mac.key_size())?,
+            drbg: RefCell::new(CTRDRBG::new(IV_PERSONALIZATION, true, false)?),
+        })
+    }
+
+    // ECB has no IV, IGE needs two blocks
+    fn iv_size(&self) -> usize {
+        match self.mode {
+            AESMode::ECB => 0,
+            AESMode::IGE => 2*BLOCK_SIZE,
+            _ => BLOCK_SIZE,
+        }
+    }
+
+    fn cipher(&self, iv: &[u8]) -> Result<Box<AES128>, AESError> {
+        match self.mode {
+            AESMode::ECB => AES128::new(&self.encryption_key, None),
+            AESMode::IGE => AES128::new_ige(&self.encryption_key, iv.try_into().unwrap()),
+            _ => AES128::new(&self.encryption_key, Some(iv.try_into().unwrap())),
+        }
+    }
+
+    fn tag(&self, data: &[u8]) -> Vec<u8> {
+        match self.mac {
+            MACAlgorithm::CMAC => cmac(AES128::new(&self.mac_key, None).unwrap().as_ref(), data).to_vec(),
+            MACAlgorithm::HMACSHA256 => hmac_sha256(&self.mac_key, data).to_vec(),
+        }
+    }
+
+    // Encrypt with a fresh random IV
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>, AESError> {
+        let mut iv = vec![0; self.iv_size()];
+        self.drbg.borrow_mut().generate(&mut iv, &[])?;
+
+        self.encrypt_with_iv(&iv, plaintext)
+    }
+
+    // The IV must never repeat under the same key (and be unpredictable for CBC)
+    pub fn encrypt_with_iv(&self, iv: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, AESError> {
+        if iv.len() != self.iv_size() {
+            return Err(AESError::WrongNonceSize(iv.len(), self.iv_size()));
+        }
+        let blocks = split_in_blocks(&padding(plaintext))?;
+        let ciphertext = self.cipher(iv)?.encrypt_blocks(&blocks, self.mode)?;
+
+        let mut result = iv.to_vec();
+        result.extend(unite_blocks(&ciphertext));
+        let tag = self.tag(&result);
+        result.extend(tag);
+
+        Ok(result)
+    }
+
+    pub fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>, AESError> {
+        let minimum = self.iv_size() + BLOCK_SIZE + self.mac.tag_size();
+        if data.len() < minimum {
+            return Err(AESError::DataTooShort(data.len(), minimum));
+        }
+        let (authenticated, tag) = data.split_at(data.len() - self.mac.tag_size());
+        if !constant_time_eq(&self.tag(authenticated), tag) {
+            return Err(AESError::AuthenticationFailed);
+        }
+
+        let (iv, ciphertext) = authenticated.split_at(self.iv_size());
+        let blocks = split_in_blocks(ciphertext)?;
+        let plaintext = self.cipher(iv)?.decrypt_blocks(&blocks, self.mode)?;
+
+        unpadding(&unite_blocks(&plaintext))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::{AESError, AESMode};
+    use super::{EncryptThenMAC, MACAlgorithm};
+
+    const MODES: [AESMode; 5] = [AESMode::ECB, AESMode::CBC, AESMode::OFB, AESMode::PCBC, AESMode::IGE];
+    const MACS: [MACAlgorithm; 2] = [MACAlgorithm::CMAC, MACAlgorithm::HMACSHA256];
+
+    #[test]
+    fn test_etm_encrypt_decrypt() {
+        for mode in MODES {
+            for mac in MACS {
+                let etm = EncryptThenMAC::new(&[0x42; 16], mode, mac).unwrap();
+                for len in [0, 1, 16, 33] {
+                    let plaintext = vec![0x61; len];
+                    let encrypted = etm.encrypt(&plaintext).unwrap();
+                    assert_eq!(etm.decrypt(&encrypted).unwrap(), plaintext);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_etm_tampering() {
+        for mode in MODES {
+            for mac in MACS {
+                let etm = EncryptThenMAC::new(&[0x42; 16], mode, mac).unwrap();
+                let encrypted = etm.encrypt(b"transfer 100 to alice").unwrap();
+
+                // Any modified byte (IV, ciphertext or tag) is rejected
+                for position in 0..encrypted.len() {
+                    let mut tampered = encrypted.clone();
+                    tampered[position] ^= 0x01;
+                    assert!(matches!(etm.decrypt(&tampered), Err(AESError::AuthenticationFailed)));
+                }
+                assert!(etm.decrypt(&encrypted[..encrypted.len()-1]).is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn test_etm_independent_keys() {
+        let etm = EncryptThenMAC::new(&[0x42; 16], AESMode::CBC, MACAlgorithm::CMAC).unwrap();
+        assert_ne!(etm.encryption_key, etm.mac_key);
+        assert_ne!(etm.encryption_key, vec![0x42; 16]);
+
+        // Random IVs: the same plaintext encrypts differently
+        assert_ne!(etm.encrypt(b"hello").unwrap(), etm.encrypt(b"hello").unwrap());
+        // The IVs come from one DRBG kept in the struct, not a new one per message
+        assert_eq!(etm.drbg.borrow().reseed_counter(), 3);
+
+        // A different master key cannot authenticate the message
+        let other = EncryptThenMAC::new(&[0x43; 16], AESMode::CBC, MACAlgorithm::CMAC).unwrap();
+        let encrypted = etm.encrypt(b"hello").unwrap();
+        assert!(matches!(other.decrypt(&encrypted), Err(AESError::AuthenticationFailed)));
+    }
+
+    #[test]
+    fn test_etm_errors() {
+        let etm = EncryptThenMAC::new(&[0x42; 16], AESMode::IGE, MACAlgorithm::HMACSHA256).unwrap();
+        assert!(matches!(etm.encrypt_with_iv(&[0; 16], b"data"), Err(AESError::WrongNonceSize(16, 32))));
+        assert!(matches!(etm.decrypt(&[0; 40]), Err(AESError::DataTooShort(40, 80))));
+    }
+}
@@ -0,0 +1,370 @@
+use crate::{utils::add_iv, AESError, AES, BLOCK_SIZE};
+
+const ROUNDS: u8 = 10;
+const MIN_RADIX: u32 = 2;
+const MAX_RADIX: u32 = 1 << 16;
+// radix^minlen must be at least one million (SP 800-38G Rev. 1)
+const MIN_DOMAIN_SIZE: u64 = 1_000_000;
+// Longest input, its length must fit in the 32-bit field of P
+const MAX_LENGTH: usize = u32::MAX as usize;
+const DEFAULT_ALPHABET: &str = "0123456789abcdefghijklmnopqrstuvwxyz";
+
+
+// Multiply the big-endian number by the radix and add the digit
+fn mul_add(number: &mut [u8], radix: u32, digit: u32) {
+    let mut carry = digit as u64;
+    for b in number.iter_mut().rev() {
+        let value = (*b as u64) * (radix as u64) + carry;
+        *b = value as u8;
+        carry = value >> 8;
+    }
+}
+
+// Divide the big-endian number by the radix, return the remainder
+fn div_rem(number: &mut [u8], radix: u32) -> u32 {
+    let mut rem = 0u64;
+    for b in number.iter_mut() {
+        let value = (rem << 8) | (*b as u64);
+        *b = (value / radix as u64) as u8;
+        rem = value % radix as u64;
+    }
+    rem as u32
+}
+
+// NUM_radix(X) written on len bytes (big-endian)
+pub(crate) fn num_radix_to_bytes(numerals: &[u32], radix: u32, len: usize) -> Vec<u8> {
+    let mut result = vec![0; len];
+    for &n in numerals {
+        mul_add(&mut result, radix, n);
+    }
+    result
+}
+
+// Last m numerals of the big-endian number written in the given radix (i.e. number mod radix^m)
+pub(crate) fn bytes_to_numerals(bytes: &[u8], radix: u32, m: usize) -> Vec<u32> {
+    let mut number = bytes.to_vec();
+    let mut result = vec![0; m];
+    for i in (0..m).rev() {
+        result[i] = div_rem(&mut number, radix);
+    }
+    result
+}
+
+// (X + Y) mod radix^m, numerals are big-endian and have the same length
+pub(crate) fn add_mod(x: &[u32], y: &[u32], radix: u32) -> Vec<u32> {
+    let mut result = vec![0; x.len()];
+    let mut carry = 0;
+    for i in (0..x.len()).rev() {
+        let sum = x[i] + y[i] + carry;
+        result[i] = sum % radix;
+        carry = sum / radix;
+    }
+    result
+}
+
+// (X - Y) mod radix^m, numerals are big-endian and have the same length
+pub(crate) fn sub_mod(x: &[u32], y: &[u32], radix: u32) -> Vec<u32> {
+    let mut result = vec![0; x.len()];
+    let mut borrow = 0;
+    for i in (0..x.len()).rev() {
+        // Never negative as y[i] < radix
+        let value = x[i] + radix - y[i] - borrow;
+        result[i] = value % radix;
+        borrow = if value < radix { 1 } else { 0 };
+    }
+    result
+}
+
+// Smallest length such that radix^len >= one million
+pub(crate) fn min_length(radix: u32) -> usize {
+    let mut domain = 1u64;
+    let mut len = 0;
+    while domain < MIN_DOMAIN_SIZE {
+        domain *= radix as u64;
+        len += 1;
+    }
+    len.max(2)
+}
+
+
+// Characters used to write numerals in the string API, shared by FF1 and FF3-1
+pub(crate) struct Alphabet {
+    chars: Vec<char>,
+}
+
+impl Alphabet {
+    // Digits then lowercase letters, so strings only work up to radix 36
+    pub(crate) fn default_for(radix: u32) -> Alphabet {
+        Alphabet { chars: DEFAULT_ALPHABET.chars().take(radix as usize).collect() }
+    }
+
+    // The characters must be distinct, their number is the radix
+    pub(crate) fn new(alphabet: &str) -> Result<Alphabet, AESError> {
+        let chars: Vec<char> = alphabet.chars().collect();
+        for (i, c) in chars.iter().enumerate() {
+            if chars[..i].contains(c) {
+                return Err(AESError::WrongAlphabet(alphabet.to_string()));
+            }
+        }
+
+        Ok(Alphabet { chars })
+    }
+
+    pub(crate) fn radix(&self) -> u32 {
+        self.chars.len() as u32
+    }
+
+    // Read the string as numerals, apply f and write the result with the same characters
+    pub(crate) fn map<F>(&self, radix: u32, s: &str, f: F) -> Result<String, AESError>
+        where F: FnOnce(&[u32]) -> Result<Vec<u32>, AESError> {
+        // One character is needed per numeral
+        if self.chars.len() != radix as usize {
+            return Err(AESError::WrongRadix(radix));
+        }
+        let numerals = s.chars().map(|c| self.chars.iter().position(|&a| a == c)
+                    .map(|p| p as u32).ok_or(AESError::NotInAlphabet(c)))
+                .collect::<Result<Vec<u32>, AESError>>()?;
+
+        Ok(f(&numerals)?.iter().map(|&n| self.chars[n as usize]).collect())
+    }
+}
+
+// Format-preserving encryption FF1 (SP 800-38G): strings over an alphabet
+// are encrypted into strings of the same length over the same alphabet
+pub struct FF1<A: AES> {
+    cipher: Box<A>,
+    radix: u32,
+    alphabet: Alphabet,
+}
+
+impl<A: AES> FF1<A> {
+    // Radix between 2 and 2^16, strings can be used only up to radix 36 (digits and lowercase letters)
+    pub fn new(key: &[u8], radix: u32) -> Result<FF1<A>, AESError> {
+        if !(MIN_RADIX..=MAX_RADIX).contains(&radix) {
+            return Err(AESError::WrongRadix(radix));
+        }
+        Ok(FF1 {
+            cipher: A::new(key, None)?,
+            radix,
+            alphabet: Alphabet::default_for(radix),
+        })
+    }
+
+    // The radix is the number of (distinct) characters of the alphabet
+    pub fn new_with_alphabet(key: &[u8], alphabet: &str) -> Result<FF1<A>, AESError> {
+        let alphabet = Alphabet::new(alphabet)?;
+        let mut ff1 = Self::new(key, alphabet.radix())?;
+        ff1.alphabet = alphabet;
+
+        Ok(ff1)
+    }
+
+    // CBC-MAC with zero IV, the input is always a multiple of the block size
+    fn prf(&self, data: &[u8]) -> [u8; BLOCK_SIZE] {
+        let mut state = [0; BLOCK_SIZE];
+        for chunk in data.chunks(BLOCK_SIZE) {
+            state = self.cipher.encrypt_block(&add_iv(&state, chunk.try_into().unwrap()));
+        }
+        state
+    }
+
+    fn check_length(&self, length: usize) -> Result<(), AESError> {
+        let min = min_length(self.radix);
+        if length < min {
+            return Err(AESError::DomainTooSmall(length, min));
+        }
+        if length > MAX_LENGTH {
+            return Err(AESError::WrongMessageLength(length, MAX_LENGTH));
+        }
+        Ok(())
+    }
+
+    fn check_numerals(&self, numerals: &[u32]) -> Result<(), AESError> {
+        self.check_length(numerals.len())?;
+        if let Some(&n) = numerals.iter().find(|&&n| n >= self.radix) {
+            return Err(AESError::NumeralOutOfRange(n, self.radix));
+        }
+        Ok(())
+    }
+
+    // Round function output y for round i, as numerals modulo radix^m
+    fn round(&self, p: &[u8], tweak: &[u8], i: u8, half: &[u32], b: usize, m: usize) -> Vec<u32> {
+        let d = 4 * b.div_ceil(4) + 4;
+
+        // Q = T || 0..0 || [i] || [NUM_radix(B)]^b, padded so that P || Q is made of blocks
+        let mut q = tweak.to_vec();
+        q.resize(tweak.len() + (BLOCK_SIZE - (tweak.len() + b + 1) % BLOCK_SIZE) % BLOCK_SIZE, 0);
+        q.push(i);
+        q.extend(num_radix_to_bytes(half, self.radix, b));
+
+        let mut input = p.to_vec();
+        input.extend(q);
+        let r = self.prf(&input);
+
+        // S = R || CIPH(R ^ [1]) || CIPH(R ^ [2]) ... truncated to d bytes
+        let mut s = r.to_vec();
+        let mut j = 1u128;
+        while s.len() < d {
+            s.extend(self.cipher.encrypt_block(&add_iv(&r, &j.to_be_bytes())));
+            j += 1;
+        }
+        s.truncate(d);
+
+        bytes_to_numerals(&s, self.radix, m)
+    }
+
+    // Common part of encryption and decryption: lengths and the fixed block P
+    fn setup(&self, tweak: &[u8], n: usize) -> (usize, usize, usize, Vec<u8>) {
+        let u = n / 2;
+        let v = n - u;
+        // b = ceil(ceil(v * log2(radix)) / 8), computed exactly as the byte length of radix^v - 1
+        let b = num_radix_to_bytes(&vec![self.radix - 1; v], self.radix, v * 2 + 1)
+                .iter().skip_while(|&&x| x == 0).count().max(1);
+
+        let mut p = vec![1, 2, 1];
+        p.extend_from_slice(&self.radix.to_be_bytes()[1..]);
+        p.push(ROUNDS);
+        p.push((u % 256) as u8);
+        p.extend_from_slice(&(n as u32).to_be_bytes());
+        p.extend_from_slice(&(tweak.len() as u32).to_be_bytes());
+
+        (u, v, b, p)
+    }
+
+    pub fn encrypt_numerals(&self, tweak: &[u8], numerals: &[u32]) -> Result<Vec<u32>, AESError> {
+        self.check_numerals(numerals)?;
+        let (u, v, b, p) = self.setup(tweak, numerals.len());
+        let mut a = numerals[..u].to_vec();
+        let mut b_half = numerals[u..].to_vec();
+
+        for i in 0..ROUNDS {
+            let m = if i % 2 == 0 { u } else { v };
+            let y = self.round(&p, tweak, i, &b_half, b, m);
+            let c = add_mod(&a, &y, self.radix);
+            a = b_half;
+            b_half = c;
+        }
+
+        a.extend(b_half);
+        Ok(a)
+    }
+
+    pub fn decrypt_numerals(&self, tweak: &[u8], numerals: &[u32]) -> Result<Vec<u32>, AESError> {
+        self.check_numerals(numerals)?;
+        let (u, v, b, p) = self.setup(tweak, numerals.len());
+        let mut a = numerals[..u].to_vec();
+        let mut b_half = numerals[u..].to_vec();
+
+        for i in (0..ROUNDS).rev() {
+            let m = if i % 2 == 0 { u } else { v };
+            let y = self.round(&p, tweak, i, &a, b, m);
+            let c = sub_mod(&b_half, &y, self.radix);
+            b_half = a;
+            a = c;
+        }
+
+        a.extend(b_half);
+        Ok(a)
+    }
+
+    // Encrypt a string written with the alphabet
+    pub fn encrypt(&self, tweak: &[u8], s: &str) -> Result<String, AESError> {
+        self.alphabet.map(self.radix, s, |numerals| self.encrypt_numerals(tweak, numerals))
+    }
+
+    pub fn decrypt(&self, tweak: &[u8], s: &str) -> Result<String, AESError> {
+        self.alphabet.map(self.radix, s, |numerals| self.decrypt_numerals(tweak, numerals))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::{aes128::AES128, utils::decode, AESError};
+    use super::{add_mod, bytes_to_numerals, min_length, num_radix_to_bytes, sub_mod, FF1};
+
+    const KEY: &str = "2b7e151628aed2a6abf7158809cf4f3c";
+
+    #[test]
+    fn test_numeral_conversions() {
+        assert_eq!(num_radix_to_bytes(&[1, 2, 3, 4], 10, 2), vec![0x04, 0xd2]);
+        assert_eq!(bytes_to_numerals(&[0x04, 0xd2], 10, 4), vec![1, 2, 3, 4]);
+        // Only the last m numerals are kept
+        assert_eq!(bytes_to_numerals(&[0x04, 0xd2], 10, 2), vec![3, 4]);
+        assert_eq!(add_mod(&[9, 9], &[0, 2], 10), vec![0, 1]);
+        assert_eq!(sub_mod(&[0, 1], &[0, 2], 10), vec![9, 9]);
+    }
+
+    #[test]
+    fn test_min_length() {
+        assert_eq!(min_length(10), 6);
+        assert_eq!(min_length(36), 4);
+        assert_eq!(min_length(2), 20);
+        assert_eq!(min_length(1 << 16), 2);
+    }
+
+    #[test]
+    fn test_ff1_samples_radix_10() {
+        // NIST FF1 samples 1 and 2
+        let ff1 = FF1::<AES128>::new(&decode(KEY).unwrap(), 10).unwrap();
+
+        assert_eq!(ff1.encrypt(&[], "0123456789").unwrap(), "2433477484");
+        assert_eq!(ff1.decrypt(&[], "2433477484").unwrap(), "0123456789");
+
+        let tweak = decode("39383736353433323130").unwrap();
+        assert_eq!(ff1.encrypt(&tweak, "0123456789").unwrap(), "6124200773");
+        assert_eq!(ff1.decrypt(&tweak, "6124200773").unwrap(), "0123456789");
+    }
+
+    #[test]
+    fn test_ff1_sample_radix_36() {
+        // NIST FF1 sample 3
+        let ff1 = FF1::<AES128>::new(&decode(KEY).unwrap(), 36).unwrap();
+        let tweak = decode("3737373770717273373737").unwrap();
+
+        assert_eq!(ff1.encrypt(&tweak, "0123456789abcdefghi").unwrap(), "a9tv40mll9kdu509eum");
+        assert_eq!(ff1.decrypt(&tweak, "a9tv40mll9kdu509eum").unwrap(), "0123456789abcdefghi");
+    }
+
+    #[test]
+    fn test_ff1_custom_alphabet() {
+        let ff1 = FF1::<AES128>::new_with_alphabet(&decode(KEY).unwrap(), "ABCDEFGHIJKLMNOPQRSTUVWXYZ").unwrap();
+        let encrypted = ff1.encrypt(b"tweak", "ACCOUNTNUMBER").unwrap();
+
+        assert_eq!(encrypted.len(), 13);
+        assert!(encrypted.chars().all(|c| c.is_ascii_uppercase()));
+        assert_eq!(ff1.decrypt(b"tweak", &encrypted).unwrap(), "ACCOUNTNUMBER");
+        assert!(matches!(ff1.encrypt(b"tweak", "ACCOUNT-NUMBER"), Err(AESError::NotInAlphabet('-'))));
+    }
+
+    #[test]
+    fn test_ff1_large_radix() {
+        let ff1 = FF1::<AES128>::new(&decode(KEY).unwrap(), 1 << 16).unwrap();
+        let numerals = [0, 65535, 1234, 42, 7];
+        let encrypted = ff1.encrypt_numerals(&[1, 2, 3], &numerals).unwrap();
+
+        assert_eq!(ff1.decrypt_numerals(&[1, 2, 3], &encrypted).unwrap(), numerals);
+    }
+
+    #[test]
+    fn test_ff1_radix_without_alphabet() {
+        let ff1 = FF1::<AES128>::new(&[0; 16], 40).unwrap();
+
+        assert!(matches!(ff1.encrypt(&[0], "0000000000"), Err(AESError::WrongRadix(40))));
+        assert!(matches!(ff1.decrypt(&[0], "0000000000"), Err(AESError::WrongRadix(40))));
+        assert!(ff1.encrypt_numerals(&[0], &[39; 10]).is_ok());
+    }
+
+    #[test]
+    fn test_ff1_domain() {
+        let ff1 = FF1::<AES128>::new(&decode(KEY).unwrap(), 10).unwrap();
+
+        assert!(matches!(ff1.encrypt(&[], "12345"), Err(AESError::DomainTooSmall(5, 6))));
+        assert!(matches!(ff1.check_length(1 << 32), Err(AESError::WrongMessageLength(4294967296, 4294967295))));
+        assert!(ff1.encrypt(&[], "123456").is_ok());
+        assert!(matches!(ff1.encrypt_numerals(&[], &[1, 2, 3, 4, 5, 10]), Err(AESError::NumeralOutOfRange(10, 10))));
+        assert!(matches!(FF1::<AES128>::new(&decode(KEY).unwrap(), 1), Err(AESError::WrongRadix(1))));
+        assert!(matches!(FF1::<AES128>::new_with_alphabet(&decode(KEY).unwrap(), "0123456780"),
+                        Err(AESError::WrongAlphabet(_))));
+    }
+}
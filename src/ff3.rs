@@ -0,0 +1,242 @@
+use crate::{ff1::{add_mod, bytes_to_numerals, min_length, num_radix_to_bytes, sub_mod, Alphabet}, AESError, AES, BLOCK_SIZE};
+
+const ROUNDS: u8 = 8;
+const MIN_RADIX: u32 = 2;
+const MAX_RADIX: u32 = 1 << 16;
+// FF3-1 tweak is 56 bits
+pub const TWEAK_SIZE: usize = 7;
+// Each half is written on 96 bits inside the AES block
+const HALF_SIZE: usize = 12;
+
+
+// Longest input: 2 * floor(log_radix(2^96)), so every half fits in 96 bits
+fn max_length(radix: u32) -> usize {
+    let limit = 1u128 << 96;
+    let mut domain = radix as u128;
+    let mut len = 0;
+    while domain <= limit {
+        domain *= radix as u128;
+        len += 1;
+    }
+    2 * len
+}
+
+// Format-preserving encryption FF3-1 (SP 800-38G Rev. 1)
+pub struct FF3<A: AES> {
+    // AES is keyed with the byte-reversed key
+    cipher: Box<A>,
+    radix: u32,
+    alphabet: Alphabet,
+}
+
+impl<A: AES> FF3<A> {
+    // Radix between 2 and 2^16, strings can be used only up to radix 36 (digits and lowercase letters)
+    pub fn new(key: &[u8], radix: u32) -> Result<FF3<A>, AESError> {
+        if !(MIN_RADIX..=MAX_RADIX).contains(&radix) {
+            return Err(AESError::WrongRadix(radix));
+        }
+        let reversed_key: Vec<u8> = key.iter().rev().copied().collect();
+
+        Ok(FF3 {
+            cipher: A::new(&reversed_key, None)?,
+            radix,
+            alphabet: Alphabet::default_for(radix),
+        })
+    }
+
+    // The radix is the number of (distinct) characters of the alphabet
+    pub fn new_with_alphabet(key: &[u8], alphabet: &str) -> Result<FF3<A>, AESError> {
+        let alphabet = Alphabet::new(alphabet)?;
+        let mut ff3 = Self::new(key, alphabet.radix())?;
+        ff3.alphabet = alphabet;
+
+        Ok(ff3)
+    }
+
+    // T_L = T[0..28] || 0000, T_R = T[32..56] || T[28..32] || 0000
+    fn split_tweak(tweak: &[u8; TWEAK_SIZE]) -> ([u8; 4], [u8; 4]) {
+        let left = [tweak[0], tweak[1], tweak[2], tweak[3] & 0xf0];
+        let right = [tweak[4], tweak[5], tweak[6], (tweak[3] & 0x0f) << 4];
+        (left, right)
+    }
+
+    fn check_numerals(&self, numerals: &[u32]) -> Result<(), AESError> {
+        let min = min_length(self.radix);
+        if numerals.len() < min {
+            return Err(AESError::DomainTooSmall(numerals.len(), min));
+        }
+        let max = max_length(self.radix);
+        if numerals.len() > max {
+            return Err(AESError::WrongMessageLength(numerals.len(), max));
+        }
+        if let Some(&n) = numerals.iter().find(|&&n| n >= self.radix) {
+            return Err(AESError::NumeralOutOfRange(n, self.radix));
+        }
+        Ok(())
+    }
+
+    // y = NUM(REVB(CIPH(REVB(W ^ [i] || [NUM_radix(REV(half))]^12)))) mod radix^m, as reversed numerals
+    fn round(&self, w: &[u8; 4], i: u8, half: &[u32], m: usize) -> Vec<u32> {
+        let mut p = [0; BLOCK_SIZE];
+        p[..4].copy_from_slice(w);
+        p[3] ^= i;
+        let reversed: Vec<u32> = half.iter().rev().copied().collect();
+        p[4..].copy_from_slice(&num_radix_to_bytes(&reversed, self.radix, HALF_SIZE));
+
+        p.reverse();
+        let mut s = self.cipher.encrypt_block(&p);
+        s.reverse();
+
+        bytes_to_numerals(&s, self.radix, m)
+    }
+
+    // Feistel network on the two tweak halves, it also runs plain FF3 (64-bit tweaks)
+    fn encrypt_with_halves(&self, left: &[u8; 4], right: &[u8; 4], numerals: &[u32]) -> Vec<u32> {
+        let u = numerals.len().div_ceil(2);
+        let v = numerals.len() - u;
+        let mut a = numerals[..u].to_vec();
+        let mut b = numerals[u..].to_vec();
+
+        for i in 0..ROUNDS {
+            let (m, w) = if i % 2 == 0 { (u, right) } else { (v, left) };
+            let y = self.round(w, i, &b, m);
+            // Numerals are added starting from the left, so work on reversed strings
+            let rev_a: Vec<u32> = a.iter().rev().copied().collect();
+            let c: Vec<u32> = add_mod(&rev_a, &y, self.radix).into_iter().rev().collect();
+            a = b;
+            b = c;
+        }
+
+        a.extend(b);
+        a
+    }
+
+    fn decrypt_with_halves(&self, left: &[u8; 4], right: &[u8; 4], numerals: &[u32]) -> Vec<u32> {
+        let u = numerals.len().div_ceil(2);
+        let v = numerals.len() - u;
+        let mut a = numerals[..u].to_vec();
+        let mut b = numerals[u..].to_vec();
+
+        for i in (0..ROUNDS).rev() {
+            let (m, w) = if i % 2 == 0 { (u, right) } else { (v, left) };
+            let y = self.round(w, i, &a, m);
+            let rev_b: Vec<u32> = b.iter().rev().copied().collect();
+            let c: Vec<u32> = sub_mod(&rev_b, &y, self.radix).into_iter().rev().collect();
+            b = a;
+            a = c;
+        }
+
+        a.extend(b);
+        a
+    }
+
+    pub fn encrypt_numerals(&self, tweak: &[u8; TWEAK_SIZE], numerals: &[u32]) -> Result<Vec<u32>, AESError> {
+        self.check_numerals(numerals)?;
+        let (left, right) = Self::split_tweak(tweak);
+        Ok(self.encrypt_with_halves(&left, &right, numerals))
+    }
+
+    pub fn decrypt_numerals(&self, tweak: &[u8; TWEAK_SIZE], numerals: &[u32]) -> Result<Vec<u32>, AESError> {
+        self.check_numerals(numerals)?;
+        let (left, right) = Self::split_tweak(tweak);
+        Ok(self.decrypt_with_halves(&left, &right, numerals))
+    }
+
+    // Encrypt a string written with the alphabet
+    pub fn encrypt(&self, tweak: &[u8; TWEAK_SIZE], s: &str) -> Result<String, AESError> {
+        self.alphabet.map(self.radix, s, |numerals| self.encrypt_numerals(tweak, numerals))
+    }
+
+    pub fn decrypt(&self, tweak: &[u8; TWEAK_SIZE], s: &str) -> Result<String, AESError> {
+        self.alphabet.map(self.radix, s, |numerals| self.decrypt_numerals(tweak, numerals))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::{aes128::AES128, utils::decode, AESError};
+    use super::{max_length, FF3};
+
+    fn numerals(s: &str, radix: u32) -> Vec<u32> {
+        s.chars().map(|c| c.to_digit(radix).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_max_length() {
+        assert_eq!(max_length(10), 56);
+        assert_eq!(max_length(2), 192);
+        assert_eq!(max_length(1 << 16), 12);
+    }
+
+    #[test]
+    fn test_ff3_samples() {
+        // NIST FF3 samples (64-bit tweak split in two halves), they check the Feistel network of FF3-1
+        let key = decode("ef4359d8d580aa4f7f036d6f04fc6a94").unwrap();
+        let vectors = [
+            (10, "d8e7920afa330a73", "890121234567890000", "750918814058654607"),
+            (10, "9a768a92f60e12d8", "890121234567890000", "018989839189395384"),
+            (10, "d8e7920afa330a73", "89012123456789000000789000000", "48598367162252569629397416226"),
+            (10, "0000000000000000", "89012123456789000000789000000", "34695224821734535122613701434"),
+            (26, "9a768a92f60e12d8", "0123456789abcdefghi", "g2pk40i992fn20cjakb"),
+        ];
+
+        for (radix, tweak, plaintext, expected) in vectors {
+            let ff3 = FF3::<AES128>::new(&key, radix).unwrap();
+            let tweak = decode(tweak).unwrap();
+            let (left, right) = (tweak[..4].try_into().unwrap(), tweak[4..].try_into().unwrap());
+
+            let result = ff3.encrypt_with_halves(&left, &right, &numerals(plaintext, radix));
+            assert_eq!(result, numerals(expected, radix));
+            assert_eq!(ff3.decrypt_with_halves(&left, &right, &result), numerals(plaintext, radix));
+        }
+    }
+
+    #[test]
+    fn test_ff3_1() {
+        let key = decode("2de79d232df5585d68ce47882ae256d6").unwrap();
+        let tweak = decode("cbd09280979564").unwrap().try_into().unwrap();
+        let ff3 = FF3::<AES128>::new(&key, 10).unwrap();
+
+        assert_eq!(ff3.encrypt(&tweak, "3992520240").unwrap(), "8901801106");
+        assert_eq!(ff3.decrypt(&tweak, "8901801106").unwrap(), "3992520240");
+    }
+
+    #[test]
+    fn test_ff3_1_radix() {
+        let key = decode("2de79d232df5585d68ce47882ae256d6").unwrap();
+        let tweak = [1, 2, 3, 4, 5, 6, 7];
+        for radix in [2, 36, 256, 1 << 16] {
+            let ff3 = FF3::<AES128>::new(&key, radix).unwrap();
+            let data: Vec<u32> = (0..max_length(radix) as u32).map(|i| (i * 7919) % radix).collect();
+            let encrypted = ff3.encrypt_numerals(&tweak, &data).unwrap();
+
+            assert_ne!(encrypted, data);
+            assert_eq!(ff3.decrypt_numerals(&tweak, &encrypted).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn test_ff3_1_domain() {
+        let key = decode("2de79d232df5585d68ce47882ae256d6").unwrap();
+        let tweak = [0; 7];
+        let ff3 = FF3::<AES128>::new(&key, 10).unwrap();
+
+        assert!(matches!(ff3.encrypt(&tweak, "12345"), Err(AESError::DomainTooSmall(5, 6))));
+        assert!(matches!(ff3.encrypt(&tweak, &"1".repeat(57)), Err(AESError::WrongMessageLength(57, 56))));
+        assert!(matches!(FF3::<AES128>::new(&key, (1 << 16) + 1), Err(AESError::WrongRadix(65537))));
+
+        let ff3 = FF3::<AES128>::new_with_alphabet(&key, "abcdefghij").unwrap();
+        assert!(matches!(ff3.encrypt(&tweak, "abcdefghik"), Err(AESError::NotInAlphabet('k'))));
+    }
+
+    #[test]
+    fn test_ff3_1_radix_without_alphabet() {
+        let ff3 = FF3::<AES128>::new(&[0; 16], 40).unwrap();
+        let tweak = [0; 7];
+
+        assert!(matches!(ff3.encrypt(&tweak, "0000000000"), Err(AESError::WrongRadix(40))));
+        assert!(matches!(ff3.decrypt(&tweak, "0000000000"), Err(AESError::WrongRadix(40))));
+        assert!(ff3.encrypt_numerals(&tweak, &[39; 10]).is_ok());
+    }
+}
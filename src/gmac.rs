@@ -0,0 +1,240 @@
+use crate::{utils::{add_iv, constant_time_eq}, AESError, AES, BLOCK_SIZE};
+
+// Recommended IV length, other lengths go through GHASH
+const IV_SIZE: usize = 12;
+// R = 11100001 || 0^120
+const GHASH_REDUCTION: u128 = 0xe1 << 120;
+// Tag lengths allowed by SP 800-38D (in bytes)
+const TAG_SIZES: [usize; 7] = [4, 8, 12, 13, 14, 15, 16];
+
+
+// Multiplication in GF(2^128) with the bit order of GCM (the first bit is x^0)
+pub(crate) fn ghash_multiplication(x: &[u8; BLOCK_SIZE], y: &[u8; BLOCK_SIZE]) -> [u8; BLOCK_SIZE] {
+    let x = u128::from_be_bytes(*x);
+    let mut v = u128::from_be_bytes(*y);
+    let mut z = 0;
+
+    for i in (0..128).rev() {
+        if (x >> i) & 1 == 1 {
+            z ^= v;
+        }
+        v = if v & 1 == 1 { (v >> 1) ^ GHASH_REDUCTION } else { v >> 1 };
+    }
+
+    z.to_be_bytes()
+}
+
+// GHASH of the data zero-padded to whole blocks, starting from state
+fn ghash_padded(h: &[u8; BLOCK_SIZE], mut state: [u8; BLOCK_SIZE], data: &[u8]) -> [u8; BLOCK_SIZE] {
+    for chunk in data.chunks(BLOCK_SIZE) {
+        let mut block = [0; BLOCK_SIZE];
+        block[..chunk.len()].copy_from_slice(chunk);
+        state = ghash_multiplication(&add_iv(&state, &block), h);
+    }
+    state
+}
+
+// Length block [len(A)]_64 || [len(C)]_64, lengths in bits
+fn length_block(aad_len: usize, text_len: usize) -> [u8; BLOCK_SIZE] {
+    (((aad_len as u128 * 8) << 64) | (text_len as u128 * 8)).to_be_bytes()
+}
+
+// Incremental GMAC (SP 800-38D): GCM with authenticated data only
+#[allow(clippy::upper_case_acronyms)]
+pub struct GMAC<'a, A: AES> {
+    cipher: &'a A,
+    // Hash subkey H = E_K(0)
+    h: [u8; BLOCK_SIZE],
+    // Pre-counter block J0
+    j0: [u8; BLOCK_SIZE],
+    state: [u8; BLOCK_SIZE],
+    buffer: Vec<u8>,
+    length: usize,
+}
+
+impl<'a, A: AES> GMAC<'a, A> {
+    // A 96-bit IV is used directly, any other (non-empty) length is hashed into J0
+    pub fn new(cipher: &'a A, iv: &[u8]) -> Result<GMAC<'a, A>, AESError> {
+        if iv.is_empty() {
+            return Err(AESError::WrongNonceSize(0, IV_SIZE));
+        }
+        let h = cipher.encrypt_block(&[0; BLOCK_SIZE]);
+
+        let j0 = if iv.len() == IV_SIZE {
+            let mut j0 = [0; BLOCK_SIZE];
+            j0[..IV_SIZE].copy_from_slice(iv);
+            j0[BLOCK_SIZE-1] = 1;
+            j0
+        } else {
+            let state = ghash_padded(&h, [0; BLOCK_SIZE], iv);
+            ghash_multiplication(&add_iv(&state, &length_block(0, iv.len())), &h)
+        };
+
+        Ok(GMAC {
+            cipher,
+            h,
+            j0,
+            state: [0; BLOCK_SIZE],
+            buffer: Vec::with_capacity(BLOCK_SIZE),
+            length: 0,
+        })
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.length += data.len();
+        self.buffer.extend_from_slice(data);
+
+        let complete = self.buffer.len() / BLOCK_SIZE * BLOCK_SIZE;
+        let blocks: Vec<u8> = self.buffer.drain(..complete).collect();
+        self.state = ghash_padded(&self.h, self.state, &blocks);
+    }
+
+    // T = E_K(J0) ^ GHASH(A || 0..0 || [len(A)]_64 || [0]_64)
+    pub fn finalize(self) -> [u8; BLOCK_SIZE] {
+        let state = ghash_padded(&self.h, self.state, &self.buffer);
+        let state = ghash_multiplication(&add_iv(&state, &length_block(self.length, 0)), &self.h);
+
+        add_iv(&self.cipher.encrypt_block(&self.j0), &state)
+    }
+
+    // Keep only the first tag_size bytes of the tag
+    pub fn finalize_truncated(self, tag_size: usize) -> Result<Vec<u8>, AESError> {
+        if !TAG_SIZES.contains(&tag_size) {
+            return Err(AESError::WrongTagSize(tag_size, BLOCK_SIZE));
+        }
+
+        Ok(self.finalize()[..tag_size].to_vec())
+    }
+
+    // Check a full or truncated tag in constant time
+    pub fn verify(self, tag: &[u8]) -> Result<(), AESError> {
+        let expected = self.finalize_truncated(tag.len())?;
+        if !constant_time_eq(&expected, tag) {
+            return Err(AESError::AuthenticationFailed);
+        }
+
+        Ok(())
+    }
+}
+
+// Compute GMAC of the data in one call
+pub fn gmac<A: AES>(cipher: &A, iv: &[u8], data: &[u8]) -> Result<[u8; BLOCK_SIZE], AESError> {
+    let mut mac = GMAC::new(cipher, iv)?;
+    mac.update(data);
+    Ok(mac.finalize())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::{aes128::AES128, utils::decode, AESError, AES};
+    use super::{ghash_multiplication, gmac, GMAC};
+
+    // GCM test vectors with an empty plaintext (key, iv, aad, tag): test case 1 of
+    // the GCM specification and CAVP gcmEncryptExtIV128 entries with PTlen = 0
+    // (AADlen = 128, counts 0 and 1, and AADlen = 160, count 0)
+    const VECTORS: [(&str, &str, &str, &str); 4] = [
+        (
+            "00000000000000000000000000000000",
+            "000000000000000000000000",
+            "",
+            "58e2fccefa7e3061367f1d57a4e7455a",
+        ),
+        (
+            "77be63708971c4e240d1cb79e8d77feb",
+            "e0e00f19fed7ba0136a797f3",
+            "7a43ec1d9c0a5a78a0b16533a6213cab",
+            "209fcc8d3675ed938e9c7166709dd946",
+        ),
+        (
+            "7680c5d3ca6154758e510f4d25b98820",
+            "f8f105f9c3df4965780321f8",
+            "c94c410194c765e3dcc7964379758ed3",
+            "94dca8edfcf90bb74b153c8d48a17930",
+        ),
+        (
+            "5211242698bed4774a090620a6ca56f3",
+            "b1e1349120b6e832ef976f5d",
+            "b6d729aab8e6416d7002b9faa794c410d8d2f193",
+            "0f247e7f9c2505de374006738018493b",
+        ),
+    ];
+
+    // IVs other than 96 bits, with the key and AAD of test cases 5 and 6 of the GCM
+    // specification; these tags are not published, they were checked against OpenSSL's GCM
+    const IV_VECTORS: [(&str, &str); 2] = [
+        ("cafebabefacedbad", "ef6995e531e81a01f5b2f7762cc60bd2"),
+        ("9313225df88406e555909c5aff5269aa6a7a9538534f7da1e4c303d2a318a728\
+          c3c0c95156809539fcf0e2429a6b525416aedbf5a0de6a57a637b39b", "7be5178ff2b73c7d6f8b4dfdde8437ec"),
+    ];
+
+    #[test]
+    fn test_ghash_multiplication() {
+        // The multiplicative identity is 0x80 00 .. 00 in GCM bit order
+        let mut one = [0; 16];
+        one[0] = 0x80;
+        let x: [u8; 16] = decode("66e94bd4ef8a2c3b884cfa59ca342b2e").unwrap().try_into().unwrap();
+
+        assert_eq!(ghash_multiplication(&x, &one), x);
+        assert_eq!(ghash_multiplication(&one, &x), x);
+        assert_eq!(ghash_multiplication(&x, &[0; 16]), [0; 16]);
+    }
+
+    #[test]
+    fn test_gmac_vectors() {
+        for (key, iv, aad, tag) in VECTORS {
+            let aes = AES128::new(&decode(key).unwrap(), None).unwrap();
+            let result = gmac(aes.as_ref(), &decode(iv).unwrap(), &decode(aad).unwrap()).unwrap();
+            assert_eq!(result.to_vec(), decode(tag).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_gmac_arbitrary_iv() {
+        let aes = AES128::new(&decode("feffe9928665731c6d6a8f9467308308").unwrap(), None).unwrap();
+        let aad = decode("feedfacedeadbeeffeedfacedeadbeefabaddad2").unwrap();
+        for (iv, tag) in IV_VECTORS {
+            let result = gmac(aes.as_ref(), &decode(iv).unwrap(), &aad).unwrap();
+            assert_eq!(result.to_vec(), decode(tag).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_gmac_incremental() {
+        let (key, iv, aad, tag) = VECTORS[3];
+        let aes = AES128::new(&decode(key).unwrap(), None).unwrap();
+        let aad = decode(aad).unwrap();
+
+        for chunk_size in [1, 3, 16, 17] {
+            let mut mac = GMAC::new(aes.as_ref(), &decode(iv).unwrap()).unwrap();
+            for chunk in aad.chunks(chunk_size) {
+                mac.update(chunk);
+            }
+            assert_eq!(mac.finalize().to_vec(), decode(tag).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_gmac_truncated() {
+        let (key, iv, aad, _) = VECTORS[1];
+        let aes = AES128::new(&decode(key).unwrap(), None).unwrap();
+        let (iv, aad) = (decode(iv).unwrap(), decode(aad).unwrap());
+
+        let mut mac = GMAC::new(aes.as_ref(), &iv).unwrap();
+        mac.update(&aad);
+        let tag = mac.finalize_truncated(12).unwrap();
+        assert_eq!(tag, decode("209fcc8d3675ed938e9c7166").unwrap());
+
+        let mut mac = GMAC::new(aes.as_ref(), &iv).unwrap();
+        mac.update(&aad);
+        assert!(mac.verify(&tag).is_ok());
+
+        let mut mac = GMAC::new(aes.as_ref(), &iv).unwrap();
+        mac.update(&aad[1..]);
+        assert!(matches!(mac.verify(&tag), Err(AESError::AuthenticationFailed)));
+
+        let mac = GMAC::new(aes.as_ref(), &iv).unwrap();
+        assert!(matches!(mac.verify(&tag[..10]), Err(AESError::WrongTagSize(10, 16))));
+        assert!(matches!(GMAC::new(aes.as_ref(), &[]), Err(AESError::WrongNonceSize(0, 12))));
+    }
+}
@@ -0,0 +1,171 @@
+use crate::{polyval::Polyval, utils::add_iv, AESError, AES, BLOCK_SIZE};
+
+
+// HCTR2 (Crowley, Huckleberry, Biggers): length-preserving tweakable encryption,
+// every bit of the output depends on every bit of the input and of the tweak
+pub struct HCTR2<A: AES> {
+    cipher: Box<A>,
+    // POLYVAL key, h = E_K(0)
+    h: [u8; BLOCK_SIZE],
+    // L = E_K(1)
+    l: [u8; BLOCK_SIZE],
+}
+
+impl<A: AES> HCTR2<A> {
+    pub fn new(key: &[u8]) -> Result<HCTR2<A>, AESError> {
+        let cipher = A::new(key, None)?;
+        let h = cipher.encrypt_block(&0u128.to_le_bytes());
+        let l = cipher.encrypt_block(&1u128.to_le_bytes());
+
+        Ok(HCTR2 { cipher, h, l })
+    }
+
+    // H(T, N) = POLYVAL(h, [2|T| + 2 or 3] || pad(T) || N || 1 || 0..0), |T| in bits
+    fn hash(&self, tweak: &[u8], data: &[u8]) -> [u8; BLOCK_SIZE] {
+        let partial = !data.len().is_multiple_of(BLOCK_SIZE);
+        let tweak_len = (tweak.len() as u128) * 8 * 2 + if partial { 3 } else { 2 };
+
+        let mut polyval = Polyval::new(&self.h);
+        polyval.update_block(&tweak_len.to_le_bytes());
+        polyval.update_padded(tweak);
+        if partial {
+            let mut padded = data.to_vec();
+            padded.push(0x01);
+            polyval.update_padded(&padded);
+        } else {
+            polyval.update_padded(data);
+        }
+
+        polyval.finalize()
+    }
+
+    // XCTR: keystream E_K(S ^ [i]) for i = 1, 2, .. (little-endian counter)
+    fn xctr(&self, s: &[u8; BLOCK_SIZE], data: &[u8]) -> Vec<u8> {
+        let mut result = Vec::with_capacity(data.len());
+        for (i, chunk) in data.chunks(BLOCK_SIZE).enumerate() {
+            let keystream = self.cipher.encrypt_block(&add_iv(s, &(i as u128 + 1).to_le_bytes()));
+            for (j, b) in chunk.iter().enumerate() {
+                result.push(b ^ keystream[j]);
+            }
+        }
+        result
+    }
+
+    // The data must be at least one block, the tweak can have any length
+    pub fn encrypt(&self, tweak: &[u8], data: &[u8]) -> Result<Vec<u8>, AESError> {
+        if data.len() < BLOCK_SIZE {
+            return Err(AESError::DataTooShort(data.len(), BLOCK_SIZE));
+        }
+        let (m, n) = data.split_at(BLOCK_SIZE);
+        let m: [u8; BLOCK_SIZE] = m.try_into().unwrap();
+
+        let mm = add_iv(&m, &self.hash(tweak, n));
+        let uu = self.cipher.encrypt_block(&mm);
+        let s = add_iv(&add_iv(&mm, &uu), &self.l);
+        let v = self.xctr(&s, n);
+        let u = add_iv(&uu, &self.hash(tweak, &v));
+
+        let mut result = u.to_vec();
+        result.extend(v);
+        Ok(result)
+    }
+
+    pub fn decrypt(&self, tweak: &[u8], data: &[u8]) -> Result<Vec<u8>, AESError> {
+        if data.len() < BLOCK_SIZE {
+            return Err(AESError::DataTooShort(data.len(), BLOCK_SIZE));
+        }
+        let (u, v) = data.split_at(BLOCK_SIZE);
+        let u: [u8; BLOCK_SIZE] = u.try_into().unwrap();
+
+        let uu = add_iv(&u, &self.hash(tweak, v));
+        let mm = self.cipher.decrypt_block(&uu);
+        let s = add_iv(&add_iv(&mm, &uu), &self.l);
+        let n = self.xctr(&s, v);
+        let m = add_iv(&mm, &self.hash(tweak, &n));
+
+        let mut result = m.to_vec();
+        result.extend(n);
+        Ok(result)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::{aes128::AES128, sha256::sha256, utils::decode, AESError};
+    use super::HCTR2;
+
+    #[test]
+    fn test_hctr2_known_answers() {
+        // No published HCTR2 vector is at hand: these come from an independent model of the
+        // paper on OpenSSL's AES, with a POLYVAL checked against OpenSSL's AES-GCM-SIV
+        let hctr2 = HCTR2::<AES128>::new(&decode("000102030405060708090a0b0c0d0e0f").unwrap()).unwrap();
+        let vectors = [
+            (&b""[..], 16, "71e3fa3f04b168b129b0f2ee156bdb93"),
+            (b"", 32, "7749077b6e549297a7cbcf2af000078b80e7f8191eb4745a2b5d0ef7c0d6ca92"),
+            (b"tweak", 17, "1ca36d21577d41545db429b14d7d571e4d"),
+            (b"a tweak longer than one single block", 48,
+             "755903b718702c2fb1c5911630f6f6beda632742fc94733677113d59582469fab0c818edf9f57b7ca797e8492aa24100"),
+        ];
+        for (tweak, len, expected) in vectors {
+            let data: Vec<u8> = (0..len).collect();
+            let encrypted = hctr2.encrypt(tweak, &data).unwrap();
+            assert_eq!(encrypted, decode(expected).unwrap());
+            assert_eq!(hctr2.decrypt(tweak, &encrypted).unwrap(), data);
+        }
+
+        // Partial last block after many blocks, with a two-block tweak
+        let data: Vec<u8> = (0..255).collect();
+        let encrypted = hctr2.encrypt(&[0; 32], &data).unwrap();
+        assert_eq!(
+            sha256(&encrypted).to_vec(),
+            decode("43f308dfc39fbfd3593895d7eca9f963513e17dd4197968ca2cff503fecc764e").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_hctr2_encrypt_decrypt() {
+        let hctr2 = HCTR2::<AES128>::new(&[0x42; 16]).unwrap();
+        for len in [16, 17, 31, 32, 33, 255, 256] {
+            let data: Vec<u8> = (0..len).map(|i| i as u8).collect();
+            for tweak in [&b""[..], b"short", b"a tweak longer than one single block"] {
+                let encrypted = hctr2.encrypt(tweak, &data).unwrap();
+
+                assert_eq!(encrypted.len(), data.len());
+                assert_ne!(encrypted, data);
+                assert_eq!(hctr2.decrypt(tweak, &encrypted).unwrap(), data);
+            }
+        }
+    }
+
+    // Count the bytes that differ between the two ciphertexts
+    fn changed_bytes(a: &[u8], b: &[u8]) -> usize {
+        a.iter().zip(b).filter(|(x, y)| x != y).count()
+    }
+
+    #[test]
+    fn test_hctr2_diffusion() {
+        let hctr2 = HCTR2::<AES128>::new(&[0x42; 16]).unwrap();
+        let data = vec![0x61; 64];
+        let encrypted = hctr2.encrypt(b"file name", &data).unwrap();
+
+        // Flipping a bit anywhere in the input changes (nearly) every byte of the output
+        for position in [0, 15, 16, 40, 63] {
+            let mut modified = data.clone();
+            modified[position] ^= 0x01;
+            let other = hctr2.encrypt(b"file name", &modified).unwrap();
+            assert!(changed_bytes(&encrypted, &other) > 56);
+        }
+
+        // Same for the tweak
+        let other = hctr2.encrypt(b"file namf", &data).unwrap();
+        assert!(changed_bytes(&encrypted, &other) > 56);
+    }
+
+    #[test]
+    fn test_hctr2_short_data() {
+        let hctr2 = HCTR2::<AES128>::new(&[0x42; 16]).unwrap();
+        assert!(matches!(hctr2.encrypt(b"", &[0; 15]), Err(AESError::DataTooShort(15, 16))));
+        assert!(matches!(hctr2.decrypt(b"", &[0; 3]), Err(AESError::DataTooShort(3, 16))));
+    }
+}
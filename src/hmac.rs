@@ -0,0 +1,47 @@
+use crate::sha256::{sha256, DIGEST_SIZE, SHA256, SHA256_BLOCK_SIZE};
+
+const IPAD: u8 = 0x36;
+const OPAD: u8 = 0x5c;
+
+
+// HMAC-SHA256 (RFC 2104): H((K ^ opad) || H((K ^ ipad) || data))
+pub fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; DIGEST_SIZE] {
+    // Keys longer than a block are hashed first, shorter ones padded with zeros
+    let mut block_key = [0; SHA256_BLOCK_SIZE];
+    if key.len() > SHA256_BLOCK_SIZE {
+        block_key[..DIGEST_SIZE].copy_from_slice(&sha256(key));
+    } else {
+        block_key[..key.len()].copy_from_slice(key);
+    }
+
+    let mut inner = SHA256::new();
+    inner.update(&block_key.map(|b| b ^ IPAD));
+    inner.update(data);
+
+    let mut outer = SHA256::new();
+    outer.update(&block_key.map(|b| b ^ OPAD));
+    outer.update(&inner.finalize());
+    outer.finalize()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::decode;
+    use super::hmac_sha256;
+
+    // RFC 4231, test cases 1, 2 and 6
+    #[test]
+    fn test_hmac_sha256() {
+        let examples: [(&[u8], &[u8], &str); 3] = [
+            (&[0x0b; 20], b"Hi There", "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7"),
+            (b"Jefe", b"what do ya want for nothing?", "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"),
+            (&[0xaa; 131], b"Test Using Larger Than Block-Size Key - Hash Key First",
+             "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"),
+        ];
+
+        for (key, data, expected) in examples {
+            assert_eq!(hmac_sha256(key, data).to_vec(), decode(expected).unwrap());
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use crate::{cmac::CMAC, AESError, AES, BLOCK_SIZE};
+
+// Allowed counter lengths r (in bits)
+const COUNTER_SIZES: [usize; 4] = [8, 16, 24, 32];
+
+
+// How the PRF outputs are chained (SP 800-108, section 5)
+#[derive(Debug, Clone, PartialEq)]
+pub enum KBKDFMode {
+    // K(i) = PRF(KI, [i] || fixed input)
+    Counter,
+    // K(i) = PRF(KI, K(i-1) || [i] || fixed input), K(0) = IV; the counter is optional
+    Feedback { iv: Vec<u8>, counter: bool },
+    // A(i) = PRF(KI, A(i-1)), A(0) = fixed input; K(i) = PRF(KI, A(i) || [i] || fixed input)
+    DoublePipeline { counter: bool },
+}
+
+// Key-based key derivation (SP 800-108) with AES-CMAC as PRF
+#[allow(clippy::upper_case_acronyms)]
+pub struct KBKDF<A: AES> {
+    cipher: Box<A>,
+    mode: KBKDFMode,
+    // Bytes used by the counter [i]
+    counter_size: usize,
+}
+
+impl<A: AES> KBKDF<A> {
+    // The counter is always placed right before the fixed input
+    pub fn new(key: &[u8], mode: KBKDFMode, counter_bits: usize) -> Result<KBKDF<A>, AESError> {
+        if !COUNTER_SIZES.contains(&counter_bits) {
+            return Err(AESError::WrongCounterSize(counter_bits));
+        }
+
+        Ok(KBKDF {
+            cipher: A::new(key, None)?,
+            mode,
+            counter_size: counter_bits / 8,
+        })
+    }
+
+    fn prf(&self, parts: &[&[u8]]) -> [u8; BLOCK_SIZE] {
+        let mut mac = CMAC::new(self.cipher.as_ref());
+        for part in parts {
+            mac.update(part);
+        }
+        mac.finalize()
+    }
+
+    // The counter must not wrap around; computed on 64 bits so a 32-bit counter
+    // does not overflow usize on 32-bit targets, where it is capped to usize::MAX
+    fn max_length(&self) -> usize {
+        let blocks = (1u64 << (8 * self.counter_size)) - 1;
+        usize::try_from(blocks * BLOCK_SIZE as u64).unwrap_or(usize::MAX)
+    }
+
+    // Derive length bytes from raw fixed input data (as in the CAVP tests)
+    pub fn derive_with_fixed_input(&self, fixed_input: &[u8], length: usize) -> Result<Vec<u8>, AESError> {
+        if length > self.max_length() {
+            return Err(AESError::RequestTooLarge(length, self.max_length()));
+        }
+        let blocks = length.div_ceil(BLOCK_SIZE);
+
+        let mut result = Vec::with_capacity(blocks * BLOCK_SIZE);
+        let mut previous = match &self.mode {
+            KBKDFMode::Feedback { iv, .. } => iv.clone(),
+            _ => fixed_input.to_vec(),
+        };
+        for i in 1..=blocks {
+            let counter = &(i as u32).to_be_bytes()[4-self.counter_size..];
+            let block = match self.mode {
+                KBKDFMode::Counter => self.prf(&[counter, fixed_input]),
+                KBKDFMode::Feedback { counter: with_counter, .. } => {
+                    let counter = if with_counter { counter } else { &[] };
+                    self.prf(&[&previous, counter, fixed_input])
+                },
+                KBKDFMode::DoublePipeline { counter: with_counter } => {
+                    let counter = if with_counter { counter } else { &[] };
+                    // Pipeline of A(i), the output is not chained
+                    let a = self.prf(&[&previous]);
+                    previous = a.to_vec();
+                    self.prf(&[&a, counter, fixed_input])
+                },
+            };
+            if let KBKDFMode::Feedback { .. } = self.mode {
+                previous = block.to_vec();
+            }
+            result.extend(block);
+        }
+
+        result.truncate(length);
+        Ok(result)
+    }
+
+    // Fixed input = Label || 0x00 || Context || [L]_32, with L the output length in bits
+    pub fn derive(&self, label: &[u8], context: &[u8], length: usize) -> Result<Vec<u8>, AESError> {
+        // The bit length must fit in [L]_32 as well as in the counter range
+        let max_length = self.max_length().min(u32::MAX as usize / 8);
+        if length > max_length {
+            return Err(AESError::RequestTooLarge(length, max_length));
+        }
+
+        let mut fixed_input = label.to_vec();
+        fixed_input.push(0);
+        fixed_input.extend_from_slice(context);
+        fixed_input.extend(((length * 8) as u32).to_be_bytes());
+
+        self.derive_with_fixed_input(&fixed_input, length)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::{aes128::AES128, utils::decode, AESError};
+    use super::{KBKDFMode, KBKDF};
+
+    const KEY: &str = "000102030405060708090a0b0c0d0e0f";
+    const IV: &str = "a0a1a2a3a4a5a6a7a8a9aaabacadaeaf";
+
+    // CAVP KBKDFCTR_gen.rsp, PRF = CMAC_AES128, CTRLOCATION = BEFORE_FIXED, RLEN = 8_BITS, COUNT = 0
+    #[test]
+    fn test_kbkdf_counter_cavp() {
+        let kbkdf = KBKDF::<AES128>::new(&decode("dff1e50ac0b69dc40f1051d46c2b069c").unwrap(), KBKDFMode::Counter, 8).unwrap();
+        let fixed_input = decode("c16e6e02c5a3dcc8d78b9ac1306877761310455b4e41469951d9e6c2245a064b\
+                                  33fd8c3b01203a7824485bf0a64060c4648b707d2607935699316ea5").unwrap();
+
+        let derived = kbkdf.derive_with_fixed_input(&fixed_input, 16).unwrap();
+        assert_eq!(derived, decode("8be8f0869b3c0ba97b71863d1b9f7813").unwrap());
+    }
+
+    // 40 bytes with label "tenant-42" and context "disk encryption", 32-bit counter;
+    // counter mode cross-checked with pyca/cryptography's KBKDFCMAC, the other
+    // modes with a direct model of SP 800-108 over its AES-CMAC
+    #[test]
+    fn test_kbkdf_modes() {
+        let examples = [
+            (KBKDFMode::Counter,
+             "3cbdedea3957e7412a1fa8b46372ad6ec83653549bc7824afed89ace7aacdc5ab993973bb6743e6d"),
+            (KBKDFMode::Feedback { iv: decode(IV).unwrap(), counter: true },
+             "13feda96a994941ae8ca08455f34e3a974ba0b33d7979067349e87fc9973fe47432de95719f89e1a"),
+            (KBKDFMode::DoublePipeline { counter: true },
+             "1f4b16b285f25367baee54581e11caab17fc42fefaa14acc90142bbdd50b266f9717d1a210b124c3"),
+        ];
+
+        for (mode, expected) in examples {
+            let kbkdf = KBKDF::<AES128>::new(&decode(KEY).unwrap(), mode, 32).unwrap();
+            let derived = kbkdf.derive(b"tenant-42", b"disk encryption", 40).unwrap();
+            assert_eq!(derived, decode(expected).unwrap());
+        }
+    }
+
+    // CAVP-style raw fixed input with an 8-bit counter and a partial last block, for the
+    // feedback and double-pipeline variants; no CAVP vector for them is at hand, the outputs
+    // come from the same SP 800-108 model over pyca/cryptography's CMAC
+    #[test]
+    fn test_kbkdf_feedback_and_pipeline() {
+        let key = decode("dff1e50ac0b69dc40f1051d46c2b069c").unwrap();
+        let fixed_input: Vec<u8> = (0..60).map(|i| (3 * i + 1) as u8).collect();
+        let examples = [
+            (KBKDFMode::Feedback { iv: Vec::new(), counter: true }, "3aa045b7990720d6218a5821ecd085d7ec701aa1"),
+            (KBKDFMode::Feedback { iv: vec![0; 16], counter: true }, "17e59312fb09cda5bf5795576fd5008207f08274"),
+            (KBKDFMode::Feedback { iv: decode(IV).unwrap(), counter: false }, "2bb7dbf94a417f5210c63a609d208cef54fb17fb"),
+            (KBKDFMode::Feedback { iv: Vec::new(), counter: false }, "0e832662b5a49e907bf676330f243c628e35d158"),
+            (KBKDFMode::DoublePipeline { counter: true }, "d1ec61a8873f6d0a0907b1bea67a235bee6c1609"),
+            (KBKDFMode::DoublePipeline { counter: false }, "8e35d158d28a5582a5337d589fc3a23209614fce"),
+        ];
+
+        for (mode, expected) in examples {
+            let kbkdf = KBKDF::<AES128>::new(&key, mode, 8).unwrap();
+            assert_eq!(kbkdf.derive_with_fixed_input(&fixed_input, 20).unwrap(), decode(expected).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_kbkdf_separation() {
+        let kbkdf = KBKDF::<AES128>::new(&decode(KEY).unwrap(), KBKDFMode::DoublePipeline { counter: false }, 32).unwrap();
+        let key = kbkdf.derive(b"tenant-1", b"mac", 16).unwrap();
+
+        assert_ne!(kbkdf.derive(b"tenant-2", b"mac", 16).unwrap(), key);
+        assert_ne!(kbkdf.derive(b"tenant-1", b"enc", 16).unwrap(), key);
+        // L is part of the input, a longer output is not an extension of a shorter one
+        assert_ne!(kbkdf.derive(b"tenant-1", b"mac", 32).unwrap()[..16], key);
+    }
+
+    #[test]
+    fn test_kbkdf_errors() {
+        assert!(matches!(KBKDF::<AES128>::new(&decode(KEY).unwrap(), KBKDFMode::Counter, 12), Err(AESError::WrongCounterSize(12))));
+
+        let kbkdf = KBKDF::<AES128>::new(&decode(KEY).unwrap(), KBKDFMode::Counter, 8).unwrap();
+        assert!(kbkdf.derive(b"", b"", 255 * 16).is_ok());
+        assert!(matches!(kbkdf.derive(b"", b"", 255 * 16 + 1), Err(AESError::RequestTooLarge(4081, 4080))));
+
+        // With a 32-bit counter the limit comes from L, which is 32 bits long
+        let kbkdf = KBKDF::<AES128>::new(&decode(KEY).unwrap(), KBKDFMode::Counter, 32).unwrap();
+        assert!(matches!(kbkdf.derive(b"", b"", 1 << 29), Err(AESError::RequestTooLarge(536870912, 536870911))));
+        #[cfg(target_pointer_width = "64")]
+        assert_eq!(kbkdf.max_length(), 0xf_ffff_fff0);
+    }
+}
@@ -36,6 +36,8 @@ pub trait KeySchedule {
 
 
 #[cfg(test)]
+// Tests from before the lints were enforced, kept as written
+#[allow(clippy::identity_op)]
 mod tests {
     use crate::{key_schedule::R_CON, AESError, BYTES_PER_ROW};
     use super::KeySchedule;
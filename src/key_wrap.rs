@@ -0,0 +1,223 @@
+use crate::{AESError, AES, BLOCK_SIZE};
+
+// Keys are processed in 64-bit semiblocks
+const SEMIBLOCK_SIZE: usize = 8;
+// Default initial value (RFC 3394)
+const DEFAULT_IV: [u8; SEMIBLOCK_SIZE] = [0xa6; SEMIBLOCK_SIZE];
+// First half of the alternative initial value, second half is the key length (RFC 5649)
+const ALTERNATIVE_IV_PREFIX: [u8; 4] = [0xa6, 0x59, 0x59, 0xa6];
+const WRAP_ROUNDS: usize = 6;
+
+
+// Wrapping function W: A = IV, then 6 passes over the semiblocks R[1..n]
+fn w<A: AES>(kek: &A, iv: &[u8; SEMIBLOCK_SIZE], data: &[u8]) -> Vec<u8> {
+    let n = data.len() / SEMIBLOCK_SIZE;
+    let mut a = *iv;
+    let mut r: Vec<[u8; SEMIBLOCK_SIZE]> = data.chunks(SEMIBLOCK_SIZE)
+            .map(|c| c.try_into().unwrap()).collect();
+
+    for j in 0..WRAP_ROUNDS {
+        for (i, ri) in r.iter_mut().enumerate() {
+            let mut block = [0; BLOCK_SIZE];
+            block[..SEMIBLOCK_SIZE].copy_from_slice(&a);
+            block[SEMIBLOCK_SIZE..].copy_from_slice(ri);
+            let b = kek.encrypt_block(&block);
+
+            // A = MSB(B) ^ t with t = n*j + i (counting from 1)
+            let t = ((n*j + i + 1) as u64).to_be_bytes();
+            for k in 0..SEMIBLOCK_SIZE {
+                a[k] = b[k] ^ t[k];
+            }
+            ri.copy_from_slice(&b[SEMIBLOCK_SIZE..]);
+        }
+    }
+
+    let mut result = a.to_vec();
+    result.extend(r.into_iter().flatten());
+    result
+}
+
+// Unwrapping function W^-1, returns the recovered IV and the data
+fn w_inverse<A: AES>(kek: &A, data: &[u8]) -> ([u8; SEMIBLOCK_SIZE], Vec<u8>) {
+    let n = data.len() / SEMIBLOCK_SIZE - 1;
+    let mut a: [u8; SEMIBLOCK_SIZE] = data[..SEMIBLOCK_SIZE].try_into().unwrap();
+    let mut r: Vec<[u8; SEMIBLOCK_SIZE]> = data[SEMIBLOCK_SIZE..].chunks(SEMIBLOCK_SIZE)
+            .map(|c| c.try_into().unwrap()).collect();
+
+    for j in (0..WRAP_ROUNDS).rev() {
+        for i in (0..n).rev() {
+            let t = ((n*j + i + 1) as u64).to_be_bytes();
+            let mut block = [0; BLOCK_SIZE];
+            for k in 0..SEMIBLOCK_SIZE {
+                block[k] = a[k] ^ t[k];
+            }
+            block[SEMIBLOCK_SIZE..].copy_from_slice(&r[i]);
+            let b = kek.decrypt_block(&block);
+
+            a.copy_from_slice(&b[..SEMIBLOCK_SIZE]);
+            r[i].copy_from_slice(&b[SEMIBLOCK_SIZE..]);
+        }
+    }
+
+    (a, r.into_iter().flatten().collect())
+}
+
+// Wrap a key of at least two semiblocks with the key-encryption key (RFC 3394)
+pub fn wrap_key<A: AES>(kek: &A, key: &[u8]) -> Result<Vec<u8>, AESError> {
+    if !key.len().is_multiple_of(SEMIBLOCK_SIZE) {
+        return Err(AESError::DataNotDivisibleInBlocks(key.len(), SEMIBLOCK_SIZE));
+    }
+    if key.len() < 2 * SEMIBLOCK_SIZE {
+        return Err(AESError::DataTooShort(key.len(), 2 * SEMIBLOCK_SIZE));
+    }
+
+    Ok(w(kek, &DEFAULT_IV, key))
+}
+
+// Unwrap a key and check that the recovered IV is the default one (RFC 3394)
+pub fn unwrap_key<A: AES>(kek: &A, wrapped: &[u8]) -> Result<Vec<u8>, AESError> {
+    if !wrapped.len().is_multiple_of(SEMIBLOCK_SIZE) {
+        return Err(AESError::DataNotDivisibleInBlocks(wrapped.len(), SEMIBLOCK_SIZE));
+    }
+    if wrapped.len() < 3 * SEMIBLOCK_SIZE {
+        return Err(AESError::DataTooShort(wrapped.len(), 3 * SEMIBLOCK_SIZE));
+    }
+
+    let (iv, key) = w_inverse(kek, wrapped);
+    if iv != DEFAULT_IV {
+        return Err(AESError::WrongKeyWrapIV(u64::from_be_bytes(iv)));
+    }
+
+    Ok(key)
+}
+
+// Wrap a key of any (non-zero) length, using the alternative IV (RFC 5649)
+pub fn wrap_key_with_padding<A: AES>(kek: &A, key: &[u8]) -> Result<Vec<u8>, AESError> {
+    if key.is_empty() || key.len() > u32::MAX as usize {
+        return Err(AESError::WrongKeyWrapLength(key.len()));
+    }
+
+    let mut iv = [0; SEMIBLOCK_SIZE];
+    iv[..4].copy_from_slice(&ALTERNATIVE_IV_PREFIX);
+    iv[4..].copy_from_slice(&(key.len() as u32).to_be_bytes());
+
+    // Pad with zeros to a multiple of the semiblock size
+    let mut padded = key.to_vec();
+    padded.resize(key.len().div_ceil(SEMIBLOCK_SIZE) * SEMIBLOCK_SIZE, 0);
+
+    // A single semiblock is encrypted together with the IV as one AES block
+    if padded.len() == SEMIBLOCK_SIZE {
+        let mut block = [0; BLOCK_SIZE];
+        block[..SEMIBLOCK_SIZE].copy_from_slice(&iv);
+        block[SEMIBLOCK_SIZE..].copy_from_slice(&padded);
+        return Ok(kek.encrypt_block(&block).to_vec());
+    }
+
+    Ok(w(kek, &iv, &padded))
+}
+
+// Unwrap a key wrapped with padding, checking the IV, the length and the padding (RFC 5649)
+pub fn unwrap_key_with_padding<A: AES>(kek: &A, wrapped: &[u8]) -> Result<Vec<u8>, AESError> {
+    if !wrapped.len().is_multiple_of(SEMIBLOCK_SIZE) {
+        return Err(AESError::DataNotDivisibleInBlocks(wrapped.len(), SEMIBLOCK_SIZE));
+    }
+    if wrapped.len() < 2 * SEMIBLOCK_SIZE {
+        return Err(AESError::DataTooShort(wrapped.len(), 2 * SEMIBLOCK_SIZE));
+    }
+
+    let (iv, mut key) = if wrapped.len() == BLOCK_SIZE {
+        let block = kek.decrypt_block(wrapped.try_into().unwrap());
+        (block[..SEMIBLOCK_SIZE].try_into().unwrap(), block[SEMIBLOCK_SIZE..].to_vec())
+    } else {
+        w_inverse(kek, wrapped)
+    };
+
+    if iv[..4] != ALTERNATIVE_IV_PREFIX {
+        return Err(AESError::WrongKeyWrapIV(u64::from_be_bytes(iv)));
+    }
+    // Message length must fit in the last semiblock
+    let length = u32::from_be_bytes(iv[4..].try_into().unwrap()) as usize;
+    if length > key.len() || length + SEMIBLOCK_SIZE <= key.len() {
+        return Err(AESError::WrongKeyWrapLength(length));
+    }
+    if key[length..].iter().any(|&b| b != 0) {
+        return Err(AESError::WrongKeyWrapPadding);
+    }
+
+    key.truncate(length);
+    Ok(key)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::{aes128::AES128, utils::decode, AESError, AES};
+    use super::{unwrap_key, unwrap_key_with_padding, w, wrap_key, wrap_key_with_padding};
+
+    const KEK: &str = "000102030405060708090a0b0c0d0e0f";
+
+    #[test]
+    fn test_wrap_key() {
+        // RFC 3394 4.1, 128 bits of key data with a 128-bit KEK
+        let kek = AES128::new(&decode(KEK).unwrap(), None).unwrap();
+        let key = decode("00112233445566778899aabbccddeeff").unwrap();
+        let wrapped = wrap_key(kek.as_ref(), &key).unwrap();
+
+        assert_eq!(wrapped, decode("1fa68b0a8112b447aef34bd8fb5a7b829d3e862371d2cfe5").unwrap());
+        assert_eq!(unwrap_key(kek.as_ref(), &wrapped).unwrap(), key);
+    }
+
+    #[test]
+    fn test_unwrap_key_integrity() {
+        let kek = AES128::new(&decode(KEK).unwrap(), None).unwrap();
+        let mut wrapped = decode("1fa68b0a8112b447aef34bd8fb5a7b829d3e862371d2cfe5").unwrap();
+        wrapped[10] ^= 1;
+
+        assert!(matches!(unwrap_key(kek.as_ref(), &wrapped), Err(AESError::WrongKeyWrapIV(_))));
+        assert!(matches!(unwrap_key(kek.as_ref(), &wrapped[..20]),
+                        Err(AESError::DataNotDivisibleInBlocks(20, 8))));
+        assert!(matches!(wrap_key(kek.as_ref(), &[0; 8]), Err(AESError::DataTooShort(8, 16))));
+    }
+
+    // RFC 5649 only has vectors for 192-bit KEKs, these use its plaintexts with a 128-bit KEK
+    #[test]
+    fn test_wrap_key_with_padding() {
+        let kek = AES128::new(&decode(KEK).unwrap(), None).unwrap();
+        let vectors = [
+            ("c37b7e6492584340bed12207808941155068f738", "e1f7176ecbd75d42e82b24f989a2816c209c6ef2d1aa94d2a3e60284900d03a2"),
+            ("466f7250617369", "be80535e12e9394c8f8df26bd9528a35"),
+            ("00112233445566778899aabbccddeeff", "2cef0c9e30de26016c230cb78bc60d51b1fe083ba0c79cd5"),
+        ];
+
+        for (key, expected) in vectors {
+            let key = decode(key).unwrap();
+            let wrapped = wrap_key_with_padding(kek.as_ref(), &key).unwrap();
+
+            assert_eq!(wrapped, decode(expected).unwrap());
+            assert_eq!(unwrap_key_with_padding(kek.as_ref(), &wrapped).unwrap(), key);
+        }
+    }
+
+    #[test]
+    fn test_unwrap_key_with_padding_integrity() {
+        let kek = AES128::new(&decode(KEK).unwrap(), None).unwrap();
+
+        // Key wrapped with the default IV is rejected
+        let wrapped = decode("1fa68b0a8112b447aef34bd8fb5a7b829d3e862371d2cfe5").unwrap();
+        assert!(matches!(unwrap_key_with_padding(kek.as_ref(), &wrapped),
+                        Err(AESError::WrongKeyWrapIV(0xa6a6a6a6a6a6a6a6))));
+
+        // Declared length bigger than the data
+        let mut iv = [0xa6, 0x59, 0x59, 0xa6, 0, 0, 0, 0];
+        iv[7] = 17;
+        let wrapped = w(kek.as_ref(), &iv, &[0x11; 16]);
+        assert!(matches!(unwrap_key_with_padding(kek.as_ref(), &wrapped),
+                        Err(AESError::WrongKeyWrapLength(17))));
+
+        // Non-zero padding
+        iv[7] = 15;
+        let wrapped = w(kek.as_ref(), &iv, &[0x11; 16]);
+        assert!(matches!(unwrap_key_with_padding(kek.as_ref(), &wrapped),
+                        Err(AESError::WrongKeyWrapPadding)));
+    }
+}
@@ -6,6 +6,35 @@ mod utils;
 mod round_operations;
 mod key_schedule;
 mod aes128;
+mod cmac;
+mod eax;
+mod ocb;
+mod siv;
+mod xts;
+mod key_wrap;
+mod xcbc;
+mod pmac;
+mod cbc_mac;
+mod cbc_mac_forgery;
+mod cbc_cs;
+mod ff1;
+mod ff3;
+mod tweakable;
+mod polyval;
+mod hctr2;
+mod eme2;
+mod poly1305;
+mod gmac;
+mod ofb_stream;
+mod ctr_drbg;
+mod aes_hash;
+mod kbkdf;
+mod sha256;
+mod hmac;
+mod etm;
+mod blake2b;
+mod aez;
+mod umac;
 
 
 pub const BLOCK_SIZE: usize = 16;
@@ -15,7 +44,11 @@ pub(crate) const BYTES_PER_ROW: usize = 4;
 pub enum AESMode {
     ECB,
     CBC,
-    OFB
+    OFB,
+    // Propagating CBC
+    PCBC,
+    // Infinite Garble Extension, needs two chaining blocks
+    IGE
 }
 
 // TODO: add tests for errors
@@ -28,6 +61,26 @@ pub enum AESError {
     WrongPaddingLength(usize, usize),
     WrongPaddingValue(u8, u8),
     DecryptedStringNotUTF8(Vec<u8>),
+    WrongTagSize(usize, usize),
+    WrongNonceSize(usize, usize),
+    TooManyComponents(usize, usize),
+    DataTooShort(usize, usize),
+    WrongKeyWrapIV(u64),
+    WrongKeyWrapLength(usize),
+    WrongKeyWrapPadding,
+    WrongMessageLength(usize, usize),
+    WrongRadix(u32),
+    WrongAlphabet(String),
+    NotInAlphabet(char),
+    NumeralOutOfRange(u32, u32),
+    DomainTooSmall(usize, usize),
+    WrongSeedLength(usize, usize),
+    RequestTooLarge(usize, usize),
+    ReseedRequired,
+    EntropySourceFailed(String),
+    WrongCounterSize(usize),
+    SectorNumberOverflow(u128),
+    AuthenticationFailed,
 }
 
 // TODO: add more advanced modes
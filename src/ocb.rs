@@ -0,0 +1,313 @@
+use crate::{utils::{add_iv, constant_time_eq, gf128_double}, AESError, AES, BLOCK_SIZE};
+
+// Biggest nonce allowed by RFC 7253 (120 bits)
+const MAX_NONCE_SIZE: usize = 15;
+// One L value for each possible number of trailing zeros of a block index
+const L_TABLE_SIZE: usize = 64;
+
+
+// OCB3 authenticated encryption (RFC 7253), one block cipher call per block
+#[allow(clippy::upper_case_acronyms)]
+pub struct OCB<A: AES> {
+    cipher: Box<A>,
+    tag_size: usize,
+    l_star: [u8; BLOCK_SIZE],
+    l_dollar: [u8; BLOCK_SIZE],
+    l: Vec<[u8; BLOCK_SIZE]>,
+}
+
+impl<A: AES> OCB<A> {
+    // Tag size is in bytes: 8, 12 or 16 (64, 96 or 128 bits)
+    pub fn new(key: &[u8], tag_size: usize) -> Result<OCB<A>, AESError> {
+        if ![8, 12, 16].contains(&tag_size) {
+            return Err(AESError::WrongTagSize(tag_size, BLOCK_SIZE));
+        }
+        let cipher = A::new(key, None)?;
+
+        // L_* = E(0), L_$ = double(L_*), L_0 = double(L_$), L_i = double(L_{i-1})
+        let l_star = cipher.encrypt_block(&[0; BLOCK_SIZE]);
+        let l_dollar = gf128_double(&l_star);
+        let mut l = vec![gf128_double(&l_dollar)];
+        for i in 1..L_TABLE_SIZE {
+            l.push(gf128_double(&l[i-1]));
+        }
+
+        Ok(OCB {
+            cipher,
+            tag_size,
+            l_star,
+            l_dollar,
+            l
+        })
+    }
+
+    // Offset for the i-th block (starting from 1) is the previous one xor L_{ntz(i)}
+    fn next_offset(&self, offset: &[u8; BLOCK_SIZE], i: usize) -> [u8; BLOCK_SIZE] {
+        add_iv(offset, &self.l[i.trailing_zeros() as usize])
+    }
+
+    // Pad a partial block with 10..0
+    fn pad(data: &[u8]) -> [u8; BLOCK_SIZE] {
+        let mut block = [0; BLOCK_SIZE];
+        block[..data.len()].copy_from_slice(data);
+        block[data.len()] = 0x80;
+        block
+    }
+
+    // Process associated data, it does not depend on the nonce
+    fn hash(&self, associated_data: &[u8]) -> [u8; BLOCK_SIZE] {
+        let mut sum = [0; BLOCK_SIZE];
+        let mut offset = [0; BLOCK_SIZE];
+
+        let mut chunks = associated_data.chunks_exact(BLOCK_SIZE);
+        for (i, chunk) in chunks.by_ref().enumerate() {
+            offset = self.next_offset(&offset, i+1);
+            let block = add_iv(chunk.try_into().unwrap(), &offset);
+            sum = add_iv(&sum, &self.cipher.encrypt_block(&block));
+        }
+
+        let rest = chunks.remainder();
+        if !rest.is_empty() {
+            offset = add_iv(&offset, &self.l_star);
+            let block = add_iv(&Self::pad(rest), &offset);
+            sum = add_iv(&sum, &self.cipher.encrypt_block(&block));
+        }
+
+        sum
+    }
+
+    // Compute the initial offset from the nonce
+    fn initial_offset(&self, nonce: &[u8]) -> Result<[u8; BLOCK_SIZE], AESError> {
+        if nonce.is_empty() || nonce.len() > MAX_NONCE_SIZE {
+            return Err(AESError::WrongNonceSize(nonce.len(), MAX_NONCE_SIZE));
+        }
+
+        // Nonce = num2str(TAGLEN mod 128, 7) || zeros || 1 || N
+        let mut full_nonce = [0; BLOCK_SIZE];
+        full_nonce[0] = (((self.tag_size * 8) % 128) << 1) as u8;
+        full_nonce[BLOCK_SIZE - nonce.len() - 1] |= 1;
+        full_nonce[BLOCK_SIZE - nonce.len()..].copy_from_slice(nonce);
+
+        // Last 6 bits select where the offset starts inside Stretch
+        let bottom = (full_nonce[BLOCK_SIZE-1] & 0x3f) as usize;
+        full_nonce[BLOCK_SIZE-1] &= 0xc0;
+        let ktop = self.cipher.encrypt_block(&full_nonce);
+
+        // Stretch = Ktop || (Ktop[1..64] xor Ktop[9..72])
+        let mut stretch = [0; BLOCK_SIZE + 8 + 1];
+        stretch[..BLOCK_SIZE].copy_from_slice(&ktop);
+        for i in 0..8 {
+            stretch[BLOCK_SIZE + i] = ktop[i] ^ ktop[i+1];
+        }
+
+        let (bytes, bits) = (bottom / 8, bottom % 8);
+        let mut offset = [0; BLOCK_SIZE];
+        for i in 0..BLOCK_SIZE {
+            offset[i] = stretch[i + bytes] << bits;
+            if bits != 0 {
+                offset[i] |= stretch[i + bytes + 1] >> (8 - bits);
+            }
+        }
+
+        Ok(offset)
+    }
+
+    // Tag = E(Checksum xor Offset xor L_$) xor HASH(A), truncated to the tag size
+    fn tag(&self, checksum: &[u8; BLOCK_SIZE], offset: &[u8; BLOCK_SIZE], associated_data: &[u8]) -> Vec<u8> {
+        let tag = self.cipher.encrypt_block(&add_iv(&add_iv(checksum, offset), &self.l_dollar));
+        let tag = add_iv(&tag, &self.hash(associated_data));
+
+        tag[..self.tag_size].to_vec()
+    }
+
+    // Return the ciphertext (same length as the plaintext) and the tag
+    pub fn encrypt(&self, nonce: &[u8], associated_data: &[u8], plaintext: &[u8])
+                    -> Result<(Vec<u8>, Vec<u8>), AESError> {
+        let mut offset = self.initial_offset(nonce)?;
+        let mut checksum = [0; BLOCK_SIZE];
+        let mut ciphertext = Vec::with_capacity(plaintext.len());
+
+        let mut chunks = plaintext.chunks_exact(BLOCK_SIZE);
+        for (i, chunk) in chunks.by_ref().enumerate() {
+            let block: [u8; BLOCK_SIZE] = chunk.try_into().unwrap();
+            offset = self.next_offset(&offset, i+1);
+            let encrypted = self.cipher.encrypt_block(&add_iv(&block, &offset));
+            ciphertext.extend_from_slice(&add_iv(&encrypted, &offset));
+            checksum = add_iv(&checksum, &block);
+        }
+
+        // Last partial block is xored with a pad
+        let rest = chunks.remainder();
+        if !rest.is_empty() {
+            offset = add_iv(&offset, &self.l_star);
+            let pad = self.cipher.encrypt_block(&offset);
+            for i in 0..rest.len() {
+                ciphertext.push(rest[i] ^ pad[i]);
+            }
+            checksum = add_iv(&checksum, &Self::pad(rest));
+        }
+
+        let tag = self.tag(&checksum, &offset, associated_data);
+        Ok((ciphertext, tag))
+    }
+
+    // Decrypt and check the tag, the plaintext is returned only if it is valid
+    pub fn decrypt(&self, nonce: &[u8], associated_data: &[u8], ciphertext: &[u8], tag: &[u8])
+                    -> Result<Vec<u8>, AESError> {
+        if tag.len() != self.tag_size {
+            return Err(AESError::WrongTagSize(tag.len(), self.tag_size));
+        }
+        let mut offset = self.initial_offset(nonce)?;
+        let mut checksum = [0; BLOCK_SIZE];
+        let mut plaintext = Vec::with_capacity(ciphertext.len());
+
+        let mut chunks = ciphertext.chunks_exact(BLOCK_SIZE);
+        for (i, chunk) in chunks.by_ref().enumerate() {
+            let block: [u8; BLOCK_SIZE] = chunk.try_into().unwrap();
+            offset = self.next_offset(&offset, i+1);
+            let decrypted = add_iv(&self.cipher.decrypt_block(&add_iv(&block, &offset)), &offset);
+            plaintext.extend_from_slice(&decrypted);
+            checksum = add_iv(&checksum, &decrypted);
+        }
+
+        let rest = chunks.remainder();
+        if !rest.is_empty() {
+            offset = add_iv(&offset, &self.l_star);
+            let pad = self.cipher.encrypt_block(&offset);
+            let decrypted: Vec<u8> = rest.iter().zip(pad).map(|(c, p)| c ^ p).collect();
+            checksum = add_iv(&checksum, &Self::pad(&decrypted));
+            plaintext.extend(decrypted);
+        }
+
+        if !constant_time_eq(&self.tag(&checksum, &offset, associated_data), tag) {
+            return Err(AESError::AuthenticationFailed);
+        }
+
+        Ok(plaintext)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::{aes128::AES128, utils::decode, AESError};
+    use super::OCB;
+
+    const KEY: &str = "000102030405060708090a0b0c0d0e0f";
+
+    // Sample results from RFC 7253 appendix A (nonce, associated data, plaintext, ciphertext || tag)
+    const VECTORS: [(&str, &str, &str, &str); 16] = [
+        ("bbaa99887766554433221100", "", "", "785407bfffc8ad9edcc5520ac9111ee6"),
+        ("bbaa99887766554433221101", "0001020304050607", "0001020304050607",
+            "6820b3657b6f615a5725bda0d3b4eb3a257c9af1f8f03009"),
+        ("bbaa99887766554433221102", "0001020304050607", "", "81017f8203f081277152fade694a0a00"),
+        ("bbaa99887766554433221103", "", "0001020304050607", "45dd69f8f5aae72414054cd1f35d82760b2cd00d2f99bfa9"),
+        ("bbaa99887766554433221104", "000102030405060708090a0b0c0d0e0f", "000102030405060708090a0b0c0d0e0f",
+            "571d535b60b277188be5147170a9a22c3ad7a4ff3835b8c5701c1ccec8fc3358"),
+        ("bbaa99887766554433221105", "000102030405060708090a0b0c0d0e0f", "", "8cf761b6902ef764462ad86498ca6b97"),
+        ("bbaa99887766554433221106", "", "000102030405060708090a0b0c0d0e0f",
+            "5ce88ec2e0692706a915c00aeb8b2396f40e1c743f52436bdf06d8fa1eca343d"),
+        ("bbaa99887766554433221107", "000102030405060708090a0b0c0d0e0f1011121314151617",
+            "000102030405060708090a0b0c0d0e0f1011121314151617",
+            "1ca2207308c87c010756104d8840ce1952f09673a448a122c92c62241051f57356d7f3c90bb0e07f"),
+        ("bbaa99887766554433221108", "000102030405060708090a0b0c0d0e0f1011121314151617", "",
+            "6dc225a071fc1b9f7c69f93b0f1e10de"),
+        ("bbaa99887766554433221109", "", "000102030405060708090a0b0c0d0e0f1011121314151617",
+            "221bd0de7fa6fe993eccd769460a0af2d6cded0c395b1c3ce725f32494b9f914d85c0b1eb38357ff"),
+        ("bbaa9988776655443322110a", "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+            "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+            "bd6f6c496201c69296c11efd138a467abd3c707924b964deaffc40319af5a48540fbba186c5553c68ad9f592a79a4240"),
+        ("bbaa9988776655443322110b", "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f", "",
+            "fe80690bee8a485d11f32965bc9d2a32"),
+        ("bbaa9988776655443322110c", "", "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+            "2942bfc773bda23cabc6acfd9bfd5835bd300f0973792ef46040c53f1432bcdfb5e1dde3bc18a5f840b52e653444d5df"),
+        ("bbaa9988776655443322110d", "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f2021222324252627",
+            "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f2021222324252627",
+            "d5ca91748410c1751ff8a2f618255b68a0a12e093ff454606e59f9c1d0ddc54b65e8628e568bad7aed07ba06a4a69483a7035490c5769e60"),
+        ("bbaa9988776655443322110e", "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f2021222324252627", "",
+            "c5cd9d1850c141e358649994ee701b68"),
+        ("bbaa9988776655443322110f", "", "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f2021222324252627",
+            "4412923493c57d5de0d700f753cce0d1d2d95060122e9f15a5ddbfc5787e50b5cc55ee507bcb084e479ad363ac366b95a98ca5f3000b1479"),
+    ];
+
+    #[test]
+    fn test_ocb_vectors() {
+        let ocb = OCB::<AES128>::new(&decode(KEY).unwrap(), 16).unwrap();
+        for (nonce, ad, msg, expected) in VECTORS {
+            let (mut ciphertext, tag) = ocb.encrypt(&decode(nonce).unwrap(), &decode(ad).unwrap(),
+                                                    &decode(msg).unwrap()).unwrap();
+            ciphertext.extend(tag);
+
+            assert_eq!(ciphertext, decode(expected).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_ocb_decrypt_vectors() {
+        let ocb = OCB::<AES128>::new(&decode(KEY).unwrap(), 16).unwrap();
+        for (nonce, ad, msg, expected) in VECTORS {
+            let expected = decode(expected).unwrap();
+            let (ciphertext, tag) = expected.split_at(expected.len() - 16);
+            let result = ocb.decrypt(&decode(nonce).unwrap(), &decode(ad).unwrap(), ciphertext, tag).unwrap();
+
+            assert_eq!(result, decode(msg).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_ocb_tag96() {
+        let key = decode("0f0e0d0c0b0a09080706050403020100").unwrap();
+        let ocb = OCB::<AES128>::new(&key, 12).unwrap();
+        let data = decode("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f2021222324252627").unwrap();
+        let (mut ciphertext, tag) = ocb.encrypt(&decode("bbaa9988776655443322110d").unwrap(), &data, &data).unwrap();
+        ciphertext.extend(tag);
+
+        let expected = "1792a4e31e0755fb03e31b22116e6c2ddf9efd6e33d536f1a0124b0a55bae884ed93481529c76b6ad0c515f4d1cdd4fdac4f02aa";
+        assert_eq!(ciphertext, decode(expected).unwrap());
+    }
+
+    // Iterated test from RFC 7253 appendix A
+    fn iterated(tag_size: usize) -> Vec<u8> {
+        let mut key = [0; 16];
+        key[15] = (tag_size * 8) as u8;
+        let ocb = OCB::<AES128>::new(&key, tag_size).unwrap();
+        let nonce = |n: u32| { let mut nonce = [0; 12]; nonce[8..].copy_from_slice(&n.to_be_bytes()); nonce };
+
+        let mut c = Vec::new();
+        for i in 0..128 {
+            let s = vec![0; i as usize];
+            let (ciphertext, tag) = ocb.encrypt(&nonce(3*i+1), &s, &s).unwrap();
+            c.extend(ciphertext);
+            c.extend(tag);
+            let (ciphertext, tag) = ocb.encrypt(&nonce(3*i+2), &[], &s).unwrap();
+            c.extend(ciphertext);
+            c.extend(tag);
+            let (ciphertext, tag) = ocb.encrypt(&nonce(3*i+3), &s, &[]).unwrap();
+            c.extend(ciphertext);
+            c.extend(tag);
+        }
+
+        let (_, tag) = ocb.encrypt(&nonce(385), &c, &[]).unwrap();
+        tag
+    }
+
+    #[test]
+    fn test_ocb_iterated() {
+        assert_eq!(iterated(16), decode("67e944d23256c5e0b6c61fa22fdf1ea2").unwrap());
+        assert_eq!(iterated(12), decode("77a3d8e73589158d25d01209").unwrap());
+        assert_eq!(iterated(8), decode("192c9b7bd90ba06a").unwrap());
+    }
+
+    #[test]
+    fn test_ocb_tampering() {
+        let ocb = OCB::<AES128>::new(&decode(KEY).unwrap(), 8).unwrap();
+        let nonce = [0x42; 12];
+        let (mut ciphertext, tag) = ocb.encrypt(&nonce, b"header", b"a message of some length").unwrap();
+
+        ciphertext[20] ^= 0x10;
+        assert!(matches!(ocb.decrypt(&nonce, b"header", &ciphertext, &tag),
+                        Err(AESError::AuthenticationFailed)));
+        assert!(matches!(ocb.encrypt(&[0; 16], b"", b""), Err(AESError::WrongNonceSize(16, 15))));
+        assert!(matches!(OCB::<AES128>::new(&decode(KEY).unwrap(), 10), Err(AESError::WrongTagSize(10, 16))));
+    }
+}
@@ -0,0 +1,112 @@
+use crate::{AESError, AES, BLOCK_SIZE};
+
+
+// OFB as a stream cipher: data of any length, the keystream position is kept
+// between calls so consecutive frames continue where the previous one stopped
+pub struct OFBStream<A: AES> {
+    cipher: Box<A>,
+    // Current output block O_i = E_K(O_{i-1}), O_0 = IV
+    keystream: [u8; BLOCK_SIZE],
+    // Bytes of the current keystream block already used
+    used: usize,
+    // Total number of bytes processed
+    position: u64,
+}
+
+impl<A: AES> OFBStream<A> {
+    pub fn new(key: &[u8], iv: &[u8; BLOCK_SIZE]) -> Result<OFBStream<A>, AESError> {
+        Ok(OFBStream {
+            cipher: A::new(key, None)?,
+            keystream: *iv,
+            // The IV itself is never used as keystream
+            used: BLOCK_SIZE,
+            position: 0,
+        })
+    }
+
+    // XOR the keystream into the data, encryption and decryption are the same operation
+    pub fn apply_keystream(&mut self, data: &mut [u8]) {
+        for byte in data.iter_mut() {
+            if self.used == BLOCK_SIZE {
+                self.keystream = self.cipher.encrypt_block(&self.keystream);
+                self.used = 0;
+            }
+            *byte ^= self.keystream[self.used];
+            self.used += 1;
+        }
+        self.position += data.len() as u64;
+    }
+
+    pub fn encrypt(&mut self, data: &[u8]) -> Vec<u8> {
+        let mut result = data.to_vec();
+        self.apply_keystream(&mut result);
+        result
+    }
+
+    pub fn decrypt(&mut self, data: &[u8]) -> Vec<u8> {
+        self.encrypt(data)
+    }
+
+    // Number of keystream bytes consumed so far
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::{aes128::AES128, utils::decode, AESMode, AES};
+    use super::OFBStream;
+
+    const KEY: &str = "2b7e151628aed2a6abf7158809cf4f3c";
+    const IV: &str = "000102030405060708090a0b0c0d0e0f";
+    const PLAINTEXT: &str = "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+                             30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710";
+    // SP 800-38A, F.4.1 OFB-AES128.Encrypt
+    const CIPHERTEXT: &str = "3b3fd92eb72dad20333449f8e83cfb4a7789508d16918f03f53c52dac54ed825\
+                              9740051e9c5fecf64344f7a82260edcc304c6528f659c77866a510d9c1d6ae5e";
+
+    fn stream() -> OFBStream<AES128> {
+        let iv: [u8; 16] = decode(IV).unwrap().try_into().unwrap();
+        OFBStream::new(&decode(KEY).unwrap(), &iv).unwrap()
+    }
+
+    #[test]
+    fn test_ofb_stream_frames() {
+        let plaintext = decode(PLAINTEXT).unwrap();
+        let ciphertext = decode(CIPHERTEXT).unwrap();
+
+        // Frames of any size give the same result as one call
+        for frame in [1, 5, 16, 21, 64] {
+            let mut encryptor = stream();
+            let mut result = Vec::new();
+            for chunk in plaintext.chunks(frame) {
+                result.extend(encryptor.encrypt(chunk));
+            }
+            assert_eq!(result, ciphertext);
+            assert_eq!(encryptor.position(), 64);
+        }
+    }
+
+    #[test]
+    fn test_ofb_stream_partial() {
+        let plaintext = decode(PLAINTEXT).unwrap();
+        let ciphertext = decode(CIPHERTEXT).unwrap();
+
+        // No padding: 37 bytes in, 37 bytes out
+        let encrypted = stream().encrypt(&plaintext[..37]);
+        assert_eq!(encrypted, ciphertext[..37]);
+        assert_eq!(stream().decrypt(&encrypted), plaintext[..37]);
+    }
+
+    #[test]
+    fn test_ofb_stream_matches_blocks() {
+        let iv: [u8; 16] = decode(IV).unwrap().try_into().unwrap();
+        let aes = AES128::new(&decode(KEY).unwrap(), Some(iv)).unwrap();
+        let blocks = [[0x11; 16], [0x22; 16]];
+        let expected: Vec<u8> = aes.encrypt_blocks(&blocks, AESMode::OFB).unwrap().concat();
+
+        assert_eq!(stream().encrypt(&blocks.concat()), expected);
+    }
+}
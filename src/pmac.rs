@@ -0,0 +1,250 @@
+use std::thread;
+use crate::{utils::{add_iv, constant_time_eq, gf128_double, gf128_half}, AESError, AES, BLOCK_SIZE};
+
+// One L value for each bit of a block index
+const L_TABLE_SIZE: usize = 64;
+
+
+// PMAC1 (Rogaway): every block is masked with a Gray-code offset and encrypted
+// independently, so the work can be split in segments and combined at the end
+#[allow(clippy::upper_case_acronyms)]
+pub struct PMAC<'a, A: AES> {
+    cipher: &'a A,
+    l: Vec<[u8; BLOCK_SIZE]>,
+    l_inverse: [u8; BLOCK_SIZE],
+    offset: [u8; BLOCK_SIZE],
+    sigma: [u8; BLOCK_SIZE],
+    // Last block is kept until we know whether it is the final one
+    buffer: Vec<u8>,
+    blocks: usize,
+}
+
+impl<'a, A: AES> PMAC<'a, A> {
+    // L(0) = E_K(0), L(i) = double(L(i-1)), L(-1) = L(0) / x
+    pub fn new(cipher: &'a A) -> PMAC<'a, A> {
+        let l0 = cipher.encrypt_block(&[0; BLOCK_SIZE]);
+        let mut l = vec![l0];
+        for i in 1..L_TABLE_SIZE {
+            l.push(gf128_double(&l[i-1]));
+        }
+
+        PMAC {
+            cipher,
+            l,
+            l_inverse: gf128_half(&l0),
+            offset: [0; BLOCK_SIZE],
+            sigma: [0; BLOCK_SIZE],
+            buffer: Vec::with_capacity(BLOCK_SIZE),
+            blocks: 0,
+        }
+    }
+
+    // Offset of the i-th block (starting from 1) is gray(i) * L, xor of L(j) for every bit j of gray(i)
+    fn offset(&self, i: usize) -> [u8; BLOCK_SIZE] {
+        let gray = i ^ (i >> 1);
+        let mut offset = [0; BLOCK_SIZE];
+        for j in 0..L_TABLE_SIZE {
+            if (gray >> j) & 1 == 1 {
+                offset = add_iv(&offset, &self.l[j]);
+            }
+        }
+
+        offset
+    }
+
+    pub fn update(&mut self, mut data: &[u8]) {
+        while !data.is_empty() {
+            // More data is coming, so a full buffer is not the last block
+            if self.buffer.len() == BLOCK_SIZE {
+                let block: [u8; BLOCK_SIZE] = self.buffer.as_slice().try_into().unwrap();
+                self.blocks += 1;
+                // Consecutive offsets differ by L(ntz(i))
+                self.offset = add_iv(&self.offset, &self.l[self.blocks.trailing_zeros() as usize]);
+                self.sigma = add_iv(&self.sigma, &self.cipher.encrypt_block(&add_iv(&block, &self.offset)));
+                self.buffer.clear();
+            }
+
+            let taken = data.len().min(BLOCK_SIZE - self.buffer.len());
+            self.buffer.extend_from_slice(&data[..taken]);
+            data = &data[taken..];
+        }
+    }
+
+    // Sum of the encrypted blocks of a segment that starts at block first_block (counting from 1).
+    // Segments must contain only full blocks and must not include the final block of the message
+    pub fn hash_segment(&self, first_block: usize, segment: &[u8]) -> Result<[u8; BLOCK_SIZE], AESError> {
+        if !segment.len().is_multiple_of(BLOCK_SIZE) {
+            return Err(AESError::DataNotDivisibleInBlocks(segment.len(), BLOCK_SIZE));
+        }
+
+        let mut sigma = [0; BLOCK_SIZE];
+        for (i, chunk) in segment.chunks(BLOCK_SIZE).enumerate() {
+            let block: [u8; BLOCK_SIZE] = chunk.try_into().unwrap();
+            let offset = self.offset(first_block + i);
+            sigma = add_iv(&sigma, &self.cipher.encrypt_block(&add_iv(&block, &offset)));
+        }
+
+        Ok(sigma)
+    }
+
+    // Combine the sum of all but the final block with the final block of the message
+    fn tag(&self, sigma: &[u8; BLOCK_SIZE], last: &[u8]) -> [u8; BLOCK_SIZE] {
+        let mut block = [0; BLOCK_SIZE];
+        block[..last.len()].copy_from_slice(last);
+
+        // Full final block is masked with L(-1), otherwise it is padded with 10..0
+        let sigma = if last.len() == BLOCK_SIZE {
+            add_iv(&add_iv(sigma, &block), &self.l_inverse)
+        } else {
+            block[last.len()] = 0x80;
+            add_iv(sigma, &block)
+        };
+
+        self.cipher.encrypt_block(&sigma)
+    }
+
+    // Compute the tag from the segment sums returned by hash_segment and the final block
+    pub fn combine(&self, sums: &[[u8; BLOCK_SIZE]], last: &[u8]) -> Result<[u8; BLOCK_SIZE], AESError> {
+        if last.len() > BLOCK_SIZE {
+            return Err(AESError::DataNotDivisibleInBlocks(last.len(), BLOCK_SIZE));
+        }
+
+        let sigma = sums.iter().fold([0; BLOCK_SIZE], |acc, s| add_iv(&acc, s));
+        Ok(self.tag(&sigma, last))
+    }
+
+    pub fn finalize(self) -> [u8; BLOCK_SIZE] {
+        self.tag(&self.sigma, &self.buffer)
+    }
+
+    // Check a full or truncated tag in constant time
+    pub fn verify(self, tag: &[u8]) -> Result<(), AESError> {
+        if tag.is_empty() || tag.len() > BLOCK_SIZE {
+            return Err(AESError::WrongTagSize(tag.len(), BLOCK_SIZE));
+        }
+        if !constant_time_eq(&self.finalize()[..tag.len()], tag) {
+            return Err(AESError::AuthenticationFailed);
+        }
+
+        Ok(())
+    }
+}
+
+// Compute PMAC of the data in one call
+pub fn pmac<A: AES>(cipher: &A, data: &[u8]) -> [u8; BLOCK_SIZE] {
+    let mut mac = PMAC::new(cipher);
+    mac.update(data);
+    mac.finalize()
+}
+
+// Compute PMAC splitting the data between the given number of threads
+pub fn pmac_parallel<A: AES + Sync>(cipher: &A, data: &[u8], threads: usize) -> [u8; BLOCK_SIZE] {
+    let mac = PMAC::new(cipher);
+
+    // Final block (possibly partial or empty) is kept for the combination step
+    let blocks = if data.is_empty() { 0 } else { (data.len() - 1) / BLOCK_SIZE };
+    let (body, last) = data.split_at(blocks * BLOCK_SIZE);
+    let blocks_per_thread = blocks.div_ceil(threads.max(1)).max(1);
+
+    let sums: Vec<[u8; BLOCK_SIZE]> = thread::scope(|s| {
+        let handles: Vec<_> = body.chunks(blocks_per_thread * BLOCK_SIZE).enumerate()
+            .map(|(i, segment)| {
+                let mac = &mac;
+                s.spawn(move || mac.hash_segment(i * blocks_per_thread + 1, segment).unwrap())
+            })
+            .collect();
+
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+
+    // The final block is never longer than a block here
+    mac.combine(&sums, last).unwrap()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::{aes128::AES128, utils::decode, AESError, AES};
+    use super::{pmac, pmac_parallel, PMAC};
+
+    const KEY: &str = "000102030405060708090a0b0c0d0e0f";
+
+    // PMAC1-AES-128 test vectors (message 00 01 02 .. of the given length, tag)
+    const VECTORS: [(usize, &str); 6] = [
+        (0, "4399572cd6ea5341b8d35876a7098af7"),
+        (3, "256ba5193c1b991b4df0c51f388a9e27"),
+        (16, "ebbd822fa458daf6dfdad7c27da76338"),
+        (20, "0412ca150bbf79058d8c75a58c993f55"),
+        (32, "e97ac04e9e5e3399ce5355cd7407bc75"),
+        (34, "5cba7d5eb24f7c86ccc54604e53d5512"),
+    ];
+
+    fn message(len: usize) -> Vec<u8> {
+        (0..len).map(|i| i as u8).collect()
+    }
+
+    #[test]
+    fn test_pmac_vectors() {
+        let aes = AES128::new(&decode(KEY).unwrap(), None).unwrap();
+        for (len, expected) in VECTORS {
+            assert_eq!(pmac(aes.as_ref(), &message(len)).to_vec(), decode(expected).unwrap());
+        }
+        assert_eq!(pmac(aes.as_ref(), &[0; 1000]).to_vec(), decode("c2c9fa1d9985f6f0d2aff915a0e8d910").unwrap());
+    }
+
+    #[test]
+    fn test_pmac_incremental() {
+        let aes = AES128::new(&decode(KEY).unwrap(), None).unwrap();
+        for (len, expected) in VECTORS {
+            for chunk_size in [1, 5, 16, 17] {
+                let mut mac = PMAC::new(aes.as_ref());
+                for chunk in message(len).chunks(chunk_size) {
+                    mac.update(chunk);
+                }
+                assert_eq!(mac.finalize().to_vec(), decode(expected).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn test_pmac_segments() {
+        let aes = AES128::new(&decode(KEY).unwrap(), None).unwrap();
+        let data = vec![0; 1000];
+        let mac = PMAC::new(aes.as_ref());
+
+        // 62 full blocks in two segments, 8 bytes left for the final block
+        let first = mac.hash_segment(1, &data[..640]).unwrap();
+        let second = mac.hash_segment(41, &data[640..992]).unwrap();
+        let tag = mac.combine(&[second, first], &data[992..]).unwrap();
+
+        assert_eq!(tag.to_vec(), decode("c2c9fa1d9985f6f0d2aff915a0e8d910").unwrap());
+        assert!(matches!(mac.hash_segment(1, &data[..10]), Err(AESError::DataNotDivisibleInBlocks(10, 16))));
+    }
+
+    #[test]
+    fn test_pmac_parallel() {
+        let aes = AES128::new(&decode(KEY).unwrap(), None).unwrap();
+        for threads in [1, 3, 8] {
+            assert_eq!(pmac_parallel(aes.as_ref(), &[0; 1000], threads).to_vec(),
+                        decode("c2c9fa1d9985f6f0d2aff915a0e8d910").unwrap());
+            for len in 0..70 {
+                assert_eq!(pmac_parallel(aes.as_ref(), &message(len), threads),
+                            pmac(aes.as_ref(), &message(len)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_pmac_verify() {
+        let aes = AES128::new(&decode(KEY).unwrap(), None).unwrap();
+        let tag = decode("5cba7d5eb24f7c86ccc54604e53d5512").unwrap();
+
+        let mut mac = PMAC::new(aes.as_ref());
+        mac.update(&message(34));
+        assert!(mac.verify(&tag[..12]).is_ok());
+
+        let mut mac = PMAC::new(aes.as_ref());
+        mac.update(&message(33));
+        assert!(matches!(mac.verify(&tag), Err(AESError::AuthenticationFailed)));
+    }
+}
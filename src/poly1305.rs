@@ -0,0 +1,236 @@
+use crate::{aes128::AES128, utils::constant_time_eq, AESError, AES, BLOCK_SIZE};
+
+// The 130-bit numbers are kept as five 26-bit limbs
+const LIMB_MASK: u32 = 0x3ffffff;
+const KEY_SIZE: usize = 32;
+
+
+// Read four little-endian bytes
+fn le32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes(bytes[..4].try_into().unwrap())
+}
+
+// Poly1305-AES (Bernstein): the message is evaluated as a polynomial in r
+// modulo 2^130 - 5, then AES_k(nonce) is added modulo 2^128
+pub struct Poly1305 {
+    // Clamped r
+    r: [u32; 5],
+    // AES_k(n)
+    s: [u8; BLOCK_SIZE],
+    // Accumulator
+    h: [u32; 5],
+    buffer: Vec<u8>,
+}
+
+impl Poly1305 {
+    // The key is k || r (16 + 16 bytes), the nonce must never be reused with the same key
+    pub fn new(key: &[u8], nonce: &[u8; BLOCK_SIZE]) -> Result<Poly1305, AESError> {
+        if key.len() != KEY_SIZE {
+            return Err(AESError::WrongKeySize(key.len(), KEY_SIZE));
+        }
+        let (k, r) = key.split_at(BLOCK_SIZE);
+        let s = AES128::new(k, None)?.encrypt_block(nonce);
+
+        // Clamping is folded into the masks
+        let r = [
+            le32(&r[0..]) & 0x3ffffff,
+            (le32(&r[3..]) >> 2) & 0x3ffff03,
+            (le32(&r[6..]) >> 4) & 0x3ffc0ff,
+            (le32(&r[9..]) >> 6) & 0x3f03fff,
+            (le32(&r[12..]) >> 8) & 0x00fffff,
+        ];
+
+        Ok(Poly1305 { r, s, h: [0; 5], buffer: Vec::with_capacity(BLOCK_SIZE) })
+    }
+
+    // h = (h + block) * r mod 2^130 - 5, hibit is the 2^128 bit of the block
+    fn process_block(&mut self, block: &[u8; BLOCK_SIZE], hibit: u32) {
+        let [r0, r1, r2, r3, r4] = self.r.map(|x| x as u64);
+        let (s1, s2, s3, s4) = (r1 * 5, r2 * 5, r3 * 5, r4 * 5);
+
+        let h = &mut self.h;
+        h[0] += le32(&block[0..]) & LIMB_MASK;
+        h[1] += (le32(&block[3..]) >> 2) & LIMB_MASK;
+        h[2] += (le32(&block[6..]) >> 4) & LIMB_MASK;
+        h[3] += (le32(&block[9..]) >> 6) & LIMB_MASK;
+        h[4] += (le32(&block[12..]) >> 8) | hibit;
+        let [h0, h1, h2, h3, h4] = h.map(|x| x as u64);
+
+        // 2^130 = 5 (mod p), so the overflowing limbs wrap around multiplied by 5
+        let mut d = [
+            h0*r0 + h1*s4 + h2*s3 + h3*s2 + h4*s1,
+            h0*r1 + h1*r0 + h2*s4 + h3*s3 + h4*s2,
+            h0*r2 + h1*r1 + h2*r0 + h3*s4 + h4*s3,
+            h0*r3 + h1*r2 + h2*r1 + h3*r0 + h4*s4,
+            h0*r4 + h1*r3 + h2*r2 + h3*r1 + h4*r0,
+        ];
+
+        // Partial carry propagation
+        for i in 0..4 {
+            d[i+1] += d[i] >> 26;
+            h[i] = (d[i] as u32) & LIMB_MASK;
+        }
+        h[4] = (d[4] as u32) & LIMB_MASK;
+        h[0] += ((d[4] >> 26) as u32) * 5;
+        h[1] += h[0] >> 26;
+        h[0] &= LIMB_MASK;
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.buffer.extend_from_slice(data);
+
+        // Keep the last bytes buffered until more data (or the end) arrives
+        let complete = self.buffer.len() / BLOCK_SIZE * BLOCK_SIZE;
+        let blocks: Vec<u8> = self.buffer.drain(..complete).collect();
+        for block in blocks.chunks_exact(BLOCK_SIZE) {
+            self.process_block(block.try_into().unwrap(), 1 << 24);
+        }
+    }
+
+    pub fn finalize(mut self) -> [u8; BLOCK_SIZE] {
+        // A final partial block gets a 1 byte appended instead of the 2^128 bit
+        if !self.buffer.is_empty() {
+            let mut block = [0; BLOCK_SIZE];
+            block[..self.buffer.len()].copy_from_slice(&self.buffer);
+            block[self.buffer.len()] = 1;
+            self.process_block(&block, 0);
+        }
+
+        // Full carry propagation
+        let h = &mut self.h;
+        for i in 1..5 {
+            h[i] += h[i-1] >> 26;
+            h[i-1] &= LIMB_MASK;
+        }
+        h[0] += (h[4] >> 26) * 5;
+        h[4] &= LIMB_MASK;
+        h[1] += h[0] >> 26;
+        h[0] &= LIMB_MASK;
+
+        // g = h - p, chosen instead of h when it does not underflow (without branches)
+        let mut g = [0u32; 5];
+        let mut carry = 5;
+        for i in 0..5 {
+            g[i] = h[i] + carry;
+            carry = g[i] >> 26;
+            g[i] &= LIMB_MASK;
+        }
+        g[4] = g[4].wrapping_add(carry << 26).wrapping_sub(1 << 26);
+        let mask = (g[4] >> 31).wrapping_sub(1);
+        for i in 0..5 {
+            h[i] = (h[i] & !mask) | (g[i] & mask);
+        }
+
+        // Pack into 128 bits and add s modulo 2^128
+        let words = [
+            h[0] | (h[1] << 26),
+            (h[1] >> 6) | (h[2] << 20),
+            (h[2] >> 12) | (h[3] << 14),
+            (h[3] >> 18) | (h[4] << 8),
+        ];
+        let mut tag = [0; BLOCK_SIZE];
+        let mut carry = 0u64;
+        for i in 0..4 {
+            let sum = words[i] as u64 + le32(&self.s[4*i..]) as u64 + carry;
+            tag[4*i..4*i+4].copy_from_slice(&(sum as u32).to_le_bytes());
+            carry = sum >> 32;
+        }
+
+        tag
+    }
+
+    pub fn verify(self, tag: &[u8]) -> Result<(), AESError> {
+        if tag.len() != BLOCK_SIZE {
+            return Err(AESError::WrongTagSize(tag.len(), BLOCK_SIZE));
+        }
+        if !constant_time_eq(&self.finalize(), tag) {
+            return Err(AESError::AuthenticationFailed);
+        }
+
+        Ok(())
+    }
+}
+
+// One-shot Poly1305-AES
+pub fn poly1305_aes(key: &[u8], nonce: &[u8; BLOCK_SIZE], data: &[u8]) -> Result<[u8; BLOCK_SIZE], AESError> {
+    let mut poly = Poly1305::new(key, nonce)?;
+    poly.update(data);
+    Ok(poly.finalize())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::{utils::decode, AESError};
+    use super::{poly1305_aes, Poly1305};
+
+    // Test vectors from "The Poly1305-AES message-authentication code", Appendix B
+    // (key, nonce, message, tag), the key is k || r
+    const VECTORS: [(&str, &str, &str, &str); 4] = [
+        (
+            "ec074c835580741701425b623235add6851fc40c3467ac0be05cc20404f3f700",
+            "fb447350c4e868c52ac3275cf9d4327e",
+            "f3f6",
+            "f4c633c3044fc145f84f335cb81953de",
+        ),
+        (
+            "75deaa25c09f208e1dc4ce6b5cad3fbfa0f3080000f46400d0c7e9076c834403",
+            "61ee09218d29b0aaed7e154a2c5509cc",
+            "",
+            "dd3fab2251f11ac759f0887129cc2ee7",
+        ),
+        (
+            "6acb5f61a7176dd320c5c1eb2edcdc7448443d0bb0d21109c89a100b5ce2c208",
+            "ae212a55399729595dea458bc621ff0e",
+            "663cea190ffb83d89593f3f476b6bc24d7e679107ea26adb8caf6652d0656136",
+            "0ee1c16bb73f0f4fd19881753c01cdbe",
+        ),
+        (
+            "e1a5668a4d5b66a5f68cc5424ed5982d12976a08c4426d0ce8a82407c4f48207",
+            "9ae831e743978d3a23527c7128149e3a",
+            "ab0812724a7f1e342742cbed374d94d136c6b8795d45b3819830f2c04491faf0990c62e48b8018b2c3e4a0fa3134cb67fa83e158c994d961c4cb21095c1bf9",
+            "5154ad0d2cb26e01274fc51148491f1b",
+        ),
+    ];
+
+    fn decode_vector(vector: &(&str, &str, &str, &str)) -> (Vec<u8>, [u8; 16], Vec<u8>, Vec<u8>) {
+        let (key, nonce, message, tag) = vector;
+        (decode(key).unwrap(), decode(nonce).unwrap().try_into().unwrap(), decode(message).unwrap(), decode(tag).unwrap())
+    }
+
+    #[test]
+    fn test_poly1305_aes() {
+        for vector in VECTORS.iter() {
+            let (key, nonce, message, tag) = decode_vector(vector);
+            assert_eq!(poly1305_aes(&key, &nonce, &message).unwrap().to_vec(), tag);
+        }
+    }
+
+    #[test]
+    fn test_poly1305_incremental() {
+        let (key, nonce, message, tag) = decode_vector(&VECTORS[3]);
+        for split in [1, 7, 16, 17, 40] {
+            let mut poly = Poly1305::new(&key, &nonce).unwrap();
+            for chunk in message.chunks(split) {
+                poly.update(chunk);
+            }
+            assert_eq!(poly.finalize().to_vec(), tag);
+        }
+    }
+
+    #[test]
+    fn test_poly1305_verify() {
+        let (key, nonce, message, mut tag) = decode_vector(&VECTORS[2]);
+
+        let mut poly = Poly1305::new(&key, &nonce).unwrap();
+        poly.update(&message);
+        assert!(poly.verify(&tag).is_ok());
+
+        tag[15] ^= 0x80;
+        let mut poly = Poly1305::new(&key, &nonce).unwrap();
+        poly.update(&message);
+        assert!(matches!(poly.verify(&tag), Err(AESError::AuthenticationFailed)));
+
+        assert!(matches!(Poly1305::new(&key[..16], &nonce), Err(AESError::WrongKeySize(16, 32))));
+    }
+}
@@ -0,0 +1,100 @@
+use crate::BLOCK_SIZE;
+
+// x^127 + x^126 + x^121 + 1, the lower part of the POLYVAL polynomial (RFC 8452)
+const POLYVAL_REDUCTION: u128 = (1 << 127) | (1 << 126) | (1 << 121) | 1;
+
+
+// Divide by x modulo the POLYVAL polynomial
+fn half(v: u128) -> u128 {
+    if v & 1 == 1 {
+        ((v ^ POLYVAL_REDUCTION) >> 1) | (1 << 127)
+    } else {
+        v >> 1
+    }
+}
+
+// dot(a, b) = a * b * x^-128, elements are 128-bit little-endian integers
+pub(crate) fn dot(a: u128, b: u128) -> u128 {
+    let mut result = 0;
+    // Every bit i of b adds a * x^i, and after it a is divided 128 - i times
+    for i in 0..128 {
+        if (b >> i) & 1 == 1 {
+            result ^= a;
+        }
+        result = half(result);
+    }
+    result
+}
+
+// POLYVAL universal hash (RFC 8452), fed with whole blocks
+pub struct Polyval {
+    h: u128,
+    state: u128,
+}
+
+impl Polyval {
+    pub fn new(h: &[u8; BLOCK_SIZE]) -> Polyval {
+        Polyval {
+            h: u128::from_le_bytes(*h),
+            state: 0,
+        }
+    }
+
+    pub fn update_block(&mut self, block: &[u8; BLOCK_SIZE]) {
+        self.state = dot(self.state ^ u128::from_le_bytes(*block), self.h);
+    }
+
+    // Process data padded with zeros to a whole number of blocks
+    pub fn update_padded(&mut self, data: &[u8]) {
+        for chunk in data.chunks(BLOCK_SIZE) {
+            let mut block = [0; BLOCK_SIZE];
+            block[..chunk.len()].copy_from_slice(chunk);
+            self.update_block(&block);
+        }
+    }
+
+    pub fn finalize(&self) -> [u8; BLOCK_SIZE] {
+        self.state.to_le_bytes()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::decode;
+    use super::Polyval;
+
+    #[test]
+    fn test_polyval() {
+        // RFC 8452 appendix A
+        let h = decode("25629347589242761d31f826ba4b757b").unwrap().try_into().unwrap();
+        let mut polyval = Polyval::new(&h);
+        polyval.update_block(&decode("4f4f95668c83dfb6401762bb2d01a262").unwrap().try_into().unwrap());
+        polyval.update_block(&decode("d1a24ddd2721d006bbe45f20d3c9f362").unwrap().try_into().unwrap());
+
+        assert_eq!(polyval.finalize().to_vec(), decode("f7a3b47b846119fae5b7866cf5e5b77e").unwrap());
+    }
+
+    #[test]
+    fn test_polyval_gcm_siv() {
+        // RFC 8452 appendix C.1, 8-byte plaintext: record authentication key, POLYVAL input and result
+        let h = decode("d9b360279694941ac5dbc6987ada7377").unwrap().try_into().unwrap();
+        let mut polyval = Polyval::new(&h);
+        polyval.update_padded(&decode("0100000000000000000000000000000000000000000000004000000000000000").unwrap());
+
+        assert_eq!(polyval.finalize().to_vec(), decode("eb93b7740962c5e49d2a90a7dc5cec74").unwrap());
+    }
+
+    #[test]
+    fn test_polyval_padded() {
+        let h = [0x42; 16];
+        let mut padded = Polyval::new(&h);
+        padded.update_padded(&[1, 2, 3]);
+        let mut block = [0; 16];
+        block[..3].copy_from_slice(&[1, 2, 3]);
+        let mut full = Polyval::new(&h);
+        full.update_block(&block);
+
+        assert_eq!(padded.finalize(), full.finalize());
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{utils::{gf_multiplication, transpose, INVERSE_S_BOX, S_BOX}, BYTES_PER_ROW};
+use crate::{utils::{array_to_matrix, gf_multiplication, matrix_to_array, transpose, INVERSE_S_BOX, S_BOX}, BLOCK_SIZE, BYTES_PER_ROW};
 
 
 pub trait Round {
@@ -42,8 +42,8 @@ pub trait Round {
 
     fn mix_columns(state: &mut [[u8; BYTES_PER_ROW]; BYTES_PER_ROW]) {
         transpose(state);
-        for i in 0..BYTES_PER_ROW {
-            Self::mix_column(&mut state[i]);
+        for column in state.iter_mut() {
+            Self::mix_column(column);
         }
         transpose(state);
     }
@@ -69,27 +69,46 @@ pub trait Round {
 
     fn inverse_mix_columns(state: &mut [[u8; BYTES_PER_ROW]; BYTES_PER_ROW]) {
         transpose(state);
-        for i in 0..BYTES_PER_ROW {
-            Self::inverse_mix_column(&mut state[i]);
+        for column in state.iter_mut() {
+            Self::inverse_mix_column(column);
         }
         transpose(state);
     }
 
     // Add current round key to the state
     fn add_round_key(state: &mut [[u8; BYTES_PER_ROW]; BYTES_PER_ROW],
-                    keys: &Vec<[[u8; BYTES_PER_ROW]; BYTES_PER_ROW]>, round: usize) {
+                    keys: &[[[u8; BYTES_PER_ROW]; BYTES_PER_ROW]], round: usize) {
         for i in 0..BYTES_PER_ROW {
             for j in 0..BYTES_PER_ROW {
                 state[i][j] ^= keys[round][i][j];
             }
         }
     }
+
+    // One full round on a block (SubBytes, ShiftRows, MixColumns, AddRoundKey),
+    // the same operation as the AESENC instruction
+    fn aes_round(block: &[u8; BLOCK_SIZE], key: &[u8; BLOCK_SIZE]) -> [u8; BLOCK_SIZE] {
+        let mut state = array_to_matrix(block);
+        Self::substitute_bytes(&mut state, false);
+        Self::shift_rows(&mut state);
+        Self::mix_columns(&mut state);
+        Self::add_round_key(&mut state, &[array_to_matrix(key)], 0);
+
+        matrix_to_array(&state)
+    }
+
+    // Full rounds with the given keys, e.g. AES4 and AES10 of AEZ
+    fn aes_rounds(block: &[u8; BLOCK_SIZE], keys: &[[u8; BLOCK_SIZE]]) -> [u8; BLOCK_SIZE] {
+        keys.iter().fold(*block, |state, key| Self::aes_round(&state, key))
+    }
 }
 
 
 #[cfg(test)]
+// Tests from before the lints were enforced, kept as written
+#[allow(clippy::clone_on_copy, clippy::useless_vec)]
 mod tests {
-    use crate::utils::transpose;
+    use crate::utils::{decode, transpose};
     use super::Round;
 
     // Define empty struct with default Trait methods
@@ -237,4 +256,16 @@ mod tests {
         let expected = [[0xd5, 0xbc, 0x50, 0x3f]; 4];
         assert_eq!(state, expected);
     }
+
+    #[test]
+    fn test_aes_round() {
+        // FIPS 197, Appendix B: the first round turns the start of round 1 into the start of round 2
+        let block = decode("193de3bea0f4e22b9ac68d2ae9f84808").unwrap().try_into().unwrap();
+        let key = decode("a0fafe1788542cb123a339392a6c7605").unwrap().try_into().unwrap();
+        let expected = decode("a49c7ff2689f352b6b5bea43026a5049").unwrap();
+
+        assert_eq!(Test::aes_round(&block, &key).to_vec(), expected);
+        assert_eq!(Test::aes_rounds(&block, &[key]).to_vec(), expected);
+        assert_eq!(Test::aes_rounds(&block, &[]), block);
+    }
 }
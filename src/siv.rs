@@ -0,0 +1,145 @@
+use crate::{cmac::cmac, utils::{add_iv, constant_time_eq, ctr_xor, gf128_double}, AESError, AES, BLOCK_SIZE};
+
+// S2V takes at most 127 strings, the plaintext is always the last one
+const MAX_ASSOCIATED_DATA: usize = 126;
+
+
+// Deterministic authenticated encryption AES-SIV (RFC 5297)
+#[allow(clippy::upper_case_acronyms)]
+pub struct SIV<A: AES> {
+    mac_cipher: Box<A>,
+    ctr_cipher: Box<A>,
+}
+
+impl<A: AES> SIV<A> {
+    // The key is twice the AES key size: first half is used by S2V, second half by CTR
+    pub fn new(key: &[u8]) -> Result<SIV<A>, AESError> {
+        let (mac_key, ctr_key) = key.split_at(key.len() / 2);
+
+        Ok(SIV {
+            mac_cipher: A::new(mac_key, None)?,
+            ctr_cipher: A::new(ctr_key, None)?,
+        })
+    }
+
+    // Pseudo-random function on a vector of strings, the last string is handled separately
+    fn s2v(&self, associated_data: &[&[u8]], plaintext: &[u8]) -> [u8; BLOCK_SIZE] {
+        let cipher = self.mac_cipher.as_ref();
+        let mut d = cmac(cipher, &[0; BLOCK_SIZE]);
+
+        for ad in associated_data {
+            d = add_iv(&gf128_double(&d), &cmac(cipher, ad));
+        }
+
+        if plaintext.len() >= BLOCK_SIZE {
+            // Xor D into the last 16 bytes of the plaintext
+            let mut t = plaintext.to_vec();
+            let start = t.len() - BLOCK_SIZE;
+            for i in 0..BLOCK_SIZE {
+                t[start + i] ^= d[i];
+            }
+            cmac(cipher, &t)
+        } else {
+            let mut padded = [0; BLOCK_SIZE];
+            padded[..plaintext.len()].copy_from_slice(plaintext);
+            padded[plaintext.len()] = 0x80;
+            cmac(cipher, &add_iv(&gf128_double(&d), &padded))
+        }
+    }
+
+    // Clear the 31st and 63rd bits (from the right) of the SIV to get the initial counter
+    fn counter(v: &[u8; BLOCK_SIZE]) -> [u8; BLOCK_SIZE] {
+        let mut q = *v;
+        q[8] &= 0x7f;
+        q[12] &= 0x7f;
+        q
+    }
+
+    // Return SIV || ciphertext, the output is 16 bytes longer than the plaintext
+    pub fn encrypt(&self, associated_data: &[&[u8]], plaintext: &[u8]) -> Result<Vec<u8>, AESError> {
+        if associated_data.len() > MAX_ASSOCIATED_DATA {
+            return Err(AESError::TooManyComponents(associated_data.len(), MAX_ASSOCIATED_DATA));
+        }
+
+        let v = self.s2v(associated_data, plaintext);
+        let mut result = v.to_vec();
+        result.append(&mut ctr_xor(self.ctr_cipher.as_ref(), &Self::counter(&v), plaintext));
+
+        Ok(result)
+    }
+
+    // Take SIV || ciphertext and return the plaintext only if the SIV matches
+    pub fn decrypt(&self, associated_data: &[&[u8]], data: &[u8]) -> Result<Vec<u8>, AESError> {
+        if associated_data.len() > MAX_ASSOCIATED_DATA {
+            return Err(AESError::TooManyComponents(associated_data.len(), MAX_ASSOCIATED_DATA));
+        }
+        if data.len() < BLOCK_SIZE {
+            return Err(AESError::WrongTagSize(data.len(), BLOCK_SIZE));
+        }
+
+        let (v, ciphertext) = data.split_at(BLOCK_SIZE);
+        let v: [u8; BLOCK_SIZE] = v.try_into().unwrap();
+        let plaintext = ctr_xor(self.ctr_cipher.as_ref(), &Self::counter(&v), ciphertext);
+
+        if !constant_time_eq(&self.s2v(associated_data, &plaintext), &v) {
+            return Err(AESError::AuthenticationFailed);
+        }
+
+        Ok(plaintext)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::{aes128::AES128, utils::decode, AESError};
+    use super::SIV;
+
+    #[test]
+    fn test_siv_deterministic() {
+        // RFC 5297 A.1
+        let key = decode("fffefdfcfbfaf9f8f7f6f5f4f3f2f1f0f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff").unwrap();
+        let ad = decode("101112131415161718191a1b1c1d1e1f2021222324252627").unwrap();
+        let plaintext = decode("112233445566778899aabbccddee").unwrap();
+        let siv = SIV::<AES128>::new(&key).unwrap();
+
+        let result = siv.encrypt(&[&ad], &plaintext).unwrap();
+        let expected = decode("85632d07c6e8f37f950acd320a2ecc9340c02b9690c4dc04daef7f6afe5c").unwrap();
+        assert_eq!(result, expected);
+        assert_eq!(siv.decrypt(&[&ad], &result).unwrap(), plaintext);
+    }
+
+    #[test]
+    fn test_siv_nonce_based() {
+        // RFC 5297 A.2, the nonce is the last associated data component
+        let key = decode("7f7e7d7c7b7a79787776757473727170404142434445464748494a4b4c4d4e4f").unwrap();
+        let ad1 = decode("00112233445566778899aabbccddeeffdeaddadadeaddadaffeeddccbbaa99887766554433221100").unwrap();
+        let ad2 = decode("102030405060708090a0").unwrap();
+        let nonce = decode("09f911029d74e35bd84156c5635688c0").unwrap();
+        let plaintext = decode("7468697320697320736f6d6520706c61696e7465787420746f20656e6372797074207573696e67205349562d414553").unwrap();
+        let siv = SIV::<AES128>::new(&key).unwrap();
+
+        let result = siv.encrypt(&[&ad1, &ad2, &nonce], &plaintext).unwrap();
+        let expected = decode("7bdb6e3b432667eb06f4d14bff2fbd0fcb900f2fddbe404326601965c889bf17dba77ceb094fa663\
+                                b7a3f748ba8af829ea64ad544a272e9c485b62a3fd5c0d").unwrap();
+        assert_eq!(result, expected);
+        assert_eq!(siv.decrypt(&[&ad1, &ad2, &nonce], &result).unwrap(), plaintext);
+    }
+
+    #[test]
+    fn test_siv_tampering() {
+        let siv = SIV::<AES128>::new(&[0x42; 32]).unwrap();
+        let mut result = siv.encrypt(&[b"record id"], b"some record").unwrap();
+
+        assert!(matches!(siv.decrypt(&[b"other id"], &result), Err(AESError::AuthenticationFailed)));
+        assert!(matches!(siv.decrypt(&[], &result), Err(AESError::AuthenticationFailed)));
+        result[20] ^= 1;
+        assert!(matches!(siv.decrypt(&[b"record id"], &result), Err(AESError::AuthenticationFailed)));
+        assert!(matches!(siv.decrypt(&[b"record id"], &result[..10]), Err(AESError::WrongTagSize(10, 16))));
+    }
+
+    #[test]
+    fn test_siv_wrong_key() {
+        assert!(matches!(SIV::<AES128>::new(&[0; 16]), Err(AESError::WrongKeySize(8, 16))));
+    }
+}
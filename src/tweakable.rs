@@ -0,0 +1,206 @@
+use crate::{utils::{add_iv, gf128_double, gf128_multiplication}, AESError, AES, BLOCK_SIZE};
+
+
+// Block cipher with an extra public input (the tweak): every tweak selects
+// a different permutation, without changing the key
+pub trait TweakableBlockCipher {
+    type Tweak;
+
+    fn encrypt_tweaked(&self, tweak: &Self::Tweak, block: &[u8; BLOCK_SIZE]) -> [u8; BLOCK_SIZE];
+    fn decrypt_tweaked(&self, tweak: &Self::Tweak, block: &[u8; BLOCK_SIZE]) -> [u8; BLOCK_SIZE];
+}
+
+// LRW (Liskov, Rivest, Wagner): C = E_K1(P ^ K2*T) ^ K2*T with multiplication in GF(2^128)
+#[allow(clippy::upper_case_acronyms)]
+pub struct LRW<A: AES> {
+    cipher: Box<A>,
+    k2: [u8; BLOCK_SIZE],
+}
+
+impl<A: AES> LRW<A> {
+    // The key is the AES key followed by the 16-byte multiplication key K2,
+    // so at least 32 bytes (the shortest AES key is one block)
+    pub fn new(key: &[u8]) -> Result<LRW<A>, AESError> {
+        if key.len() < 2 * BLOCK_SIZE {
+            return Err(AESError::WrongKeySize(key.len(), 2 * BLOCK_SIZE));
+        }
+        let (k1, k2) = key.split_at(key.len() - BLOCK_SIZE);
+
+        Ok(LRW {
+            cipher: A::new(k1, None)?,
+            k2: k2.try_into().unwrap(),
+        })
+    }
+}
+
+impl<A: AES> TweakableBlockCipher for LRW<A> {
+    type Tweak = [u8; BLOCK_SIZE];
+
+    fn encrypt_tweaked(&self, tweak: &Self::Tweak, block: &[u8; BLOCK_SIZE]) -> [u8; BLOCK_SIZE] {
+        let mask = gf128_multiplication(&self.k2, tweak);
+        add_iv(&self.cipher.encrypt_block(&add_iv(block, &mask)), &mask)
+    }
+
+    fn decrypt_tweaked(&self, tweak: &Self::Tweak, block: &[u8; BLOCK_SIZE]) -> [u8; BLOCK_SIZE] {
+        let mask = gf128_multiplication(&self.k2, tweak);
+        add_iv(&self.cipher.decrypt_block(&add_iv(block, &mask)), &mask)
+    }
+}
+
+// XEX (Rogaway): the tweak is a nonce N and a block index i, the mask is 2^i * E_K(N).
+// Only one key is needed, the same AES instance makes the mask and encrypts the data
+#[allow(clippy::upper_case_acronyms)]
+pub struct XEX<A: AES> {
+    cipher: Box<A>,
+}
+
+impl<A: AES> XEX<A> {
+    pub fn new(key: &[u8]) -> Result<XEX<A>, AESError> {
+        Ok(XEX { cipher: A::new(key, None)? })
+    }
+
+    fn mask(&self, tweak: &([u8; BLOCK_SIZE], u32)) -> [u8; BLOCK_SIZE] {
+        let (nonce, index) = tweak;
+        let mut mask = self.cipher.encrypt_block(nonce);
+        for _ in 0..*index {
+            mask = gf128_double(&mask);
+        }
+        mask
+    }
+
+    // Consecutive blocks with indices first_index, first_index + 1, ...:
+    // the mask is computed once and then doubled for every block
+    pub fn encrypt_blocks(&self, nonce: &[u8; BLOCK_SIZE], first_index: u32, blocks: &[[u8; BLOCK_SIZE]])
+                            -> Vec<[u8; BLOCK_SIZE]> {
+        let mut mask = self.mask(&(*nonce, first_index));
+        blocks.iter().map(|block| {
+            let result = add_iv(&self.cipher.encrypt_block(&add_iv(block, &mask)), &mask);
+            mask = gf128_double(&mask);
+            result
+        }).collect()
+    }
+
+    pub fn decrypt_blocks(&self, nonce: &[u8; BLOCK_SIZE], first_index: u32, blocks: &[[u8; BLOCK_SIZE]])
+                            -> Vec<[u8; BLOCK_SIZE]> {
+        let mut mask = self.mask(&(*nonce, first_index));
+        blocks.iter().map(|block| {
+            let result = add_iv(&self.cipher.decrypt_block(&add_iv(block, &mask)), &mask);
+            mask = gf128_double(&mask);
+            result
+        }).collect()
+    }
+}
+
+impl<A: AES> TweakableBlockCipher for XEX<A> {
+    type Tweak = ([u8; BLOCK_SIZE], u32);
+
+    fn encrypt_tweaked(&self, tweak: &Self::Tweak, block: &[u8; BLOCK_SIZE]) -> [u8; BLOCK_SIZE] {
+        let mask = self.mask(tweak);
+        add_iv(&self.cipher.encrypt_block(&add_iv(block, &mask)), &mask)
+    }
+
+    fn decrypt_tweaked(&self, tweak: &Self::Tweak, block: &[u8; BLOCK_SIZE]) -> [u8; BLOCK_SIZE] {
+        let mask = self.mask(tweak);
+        add_iv(&self.cipher.decrypt_block(&add_iv(block, &mask)), &mask)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use crate::{aes128::AES128, utils::decode, AESError, AES};
+    use super::{TweakableBlockCipher, LRW, XEX};
+
+    // Encrypt 256 different blocks under the tweak
+    fn permutation<T: TweakableBlockCipher>(cipher: &T, tweak: &T::Tweak) -> Vec<[u8; 16]> {
+        (0..=255u8).map(|i| cipher.encrypt_tweaked(tweak, &[i; 16])).collect()
+    }
+
+    // Same inputs under two tweaks: both are injective and they never agree
+    fn check_independent<T: TweakableBlockCipher>(cipher: &T, first: &T::Tweak, second: &T::Tweak) {
+        let a = permutation(cipher, first);
+        let b = permutation(cipher, second);
+
+        assert_eq!(a.iter().collect::<HashSet<_>>().len(), 256);
+        assert_eq!(b.iter().collect::<HashSet<_>>().len(), 256);
+        for i in 0..256 {
+            assert_ne!(a[i], b[i]);
+        }
+    }
+
+    fn check_inverse<T: TweakableBlockCipher>(cipher: &T, tweak: &T::Tweak) {
+        for i in 0..=255u8 {
+            let block = [i; 16];
+            assert_eq!(cipher.decrypt_tweaked(tweak, &cipher.encrypt_tweaked(tweak, &block)), block);
+        }
+    }
+
+    #[test]
+    fn test_lrw_tweaks() {
+        let mut key = [0x42; 32];
+        key[16..].copy_from_slice(&[0x17; 16]);
+        let lrw = LRW::<AES128>::new(&key).unwrap();
+
+        check_independent(&lrw, &[0; 16], &[1; 16]);
+        check_independent(&lrw, &[1; 16], &[2; 16]);
+        check_inverse(&lrw, &[0x33; 16]);
+    }
+
+    #[test]
+    fn test_lrw_zero_tweak() {
+        // With T = 0 the mask vanishes and LRW is plain AES under K1
+        let key = [0x42; 32];
+        let lrw = LRW::<AES128>::new(&key).unwrap();
+        let aes = AES128::new(&key[..16], None).unwrap();
+
+        assert_eq!(lrw.encrypt_tweaked(&[0; 16], &[0x55; 16]), aes.encrypt_block(&[0x55; 16]));
+        assert!(matches!(LRW::<AES128>::new(&[0; 20]), Err(AESError::WrongKeySize(20, 32))));
+    }
+
+    // IEEE P1619 LRW-32-AES vectors 1-3 (key1 || key2, tweak index, ciphertext of "0123456789ABCDEF")
+    #[test]
+    fn test_lrw_vectors() {
+        let vectors = [
+            ("4562ac25f828176d4c268414b5680185258e2a05e73e9d03ee5a830ccc094c87",
+             "00000000000000000000000000000001", "f1b273cd65a3df5fe95d489254634eb8"),
+            ("59704714f557478cd779e80f548879440d48f0b7b15a53ea1caa6b29c2cafbaf",
+             "00000000000000000000000000000002", "00c82bae95bbcde5274f0769b260e136"),
+            ("d82a9134b26a565030fe69e2377f9847cdf90b160c648fb6b00d0d1bae85871f",
+             "00000000000000000000000200000000", "76322183ed8ff182f9596203690e5e01"),
+        ];
+        let plaintext: [u8; 16] = *b"0123456789ABCDEF";
+
+        for (key, tweak, expected) in vectors {
+            let lrw = LRW::<AES128>::new(&decode(key).unwrap()).unwrap();
+            let tweak: [u8; 16] = decode(tweak).unwrap().try_into().unwrap();
+            let ciphertext = lrw.encrypt_tweaked(&tweak, &plaintext);
+
+            assert_eq!(ciphertext.to_vec(), decode(expected).unwrap());
+            assert_eq!(lrw.decrypt_tweaked(&tweak, &ciphertext), plaintext);
+        }
+    }
+
+    #[test]
+    fn test_xex_tweaks() {
+        let xex = XEX::<AES128>::new(&[0x42; 16]).unwrap();
+
+        // Different indices with the same nonce, and different nonces with the same index
+        check_independent(&xex, &([0; 16], 0), &([0; 16], 1));
+        check_independent(&xex, &([0; 16], 5), &([1; 16], 5));
+        check_inverse(&xex, &([0x33; 16], 1000));
+    }
+
+    #[test]
+    fn test_xex_blocks() {
+        // Incremental masks give the same result as one tweaked call per block
+        let xex = XEX::<AES128>::new(&[0x42; 16]).unwrap();
+        let blocks: Vec<[u8; 16]> = (0..20u8).map(|i| [i; 16]).collect();
+        let encrypted = xex.encrypt_blocks(&[7; 16], 3, &blocks);
+
+        for (i, (block, result)) in blocks.iter().zip(&encrypted).enumerate() {
+            assert_eq!(xex.encrypt_tweaked(&([7; 16], 3 + i as u32), block), *result);
+        }
+        assert_eq!(xex.decrypt_blocks(&[7; 16], 3, &encrypted), blocks);
+    }
+}
@@ -0,0 +1,320 @@
+use crate::{aes128::AES128, utils::constant_time_eq, AESError, AES, BLOCK_SIZE};
+
+// Tags of 32, 64, 96 and 128 bits
+const TAG_SIZES: [usize; 4] = [4, 8, 12, 16];
+// Each UHASH iteration produces 32 bits
+const ITERATION_SIZE: usize = 4;
+// L1-HASH chunk and key length
+const L1_KEY_SIZE: usize = 1024;
+// Key bytes by which consecutive iterations shift the L1 key (Toeplitz)
+const L1_KEY_SHIFT: usize = 16;
+const L2_KEY_SIZE: usize = 24;
+const L3_KEY1_SIZE: usize = 64;
+const L3_KEY2_SIZE: usize = 4;
+// Above this many bytes L2-HASH switches from 64-bit to 128-bit polynomial
+const L2_POLY64_LIMIT: usize = 1 << 17;
+
+const MASK64: u64 = 0x01ffffff01ffffff;
+const MASK128: u128 = 0x01ffffff01ffffff01ffffff01ffffff;
+const P36: u64 = (1 << 36) - 5;
+const P64: u64 = 0u64.wrapping_sub(59);
+const P128: u128 = 0u128.wrapping_sub(159);
+
+
+// KDF: AES_K([index]_64 || [i]_64) for i = 1, 2, .., truncated to size bytes
+fn kdf(cipher: &AES128, index: u64, size: usize) -> Vec<u8> {
+    let mut result = Vec::with_capacity(size.next_multiple_of(BLOCK_SIZE));
+    let mut i: u64 = 1;
+    while result.len() < size {
+        let mut block = [0; BLOCK_SIZE];
+        block[..8].copy_from_slice(&index.to_be_bytes());
+        block[8..].copy_from_slice(&i.to_be_bytes());
+        result.extend(cipher.encrypt_block(&block));
+        i += 1;
+    }
+    result.truncate(size);
+    result
+}
+
+fn be32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes(bytes[..4].try_into().unwrap())
+}
+
+fn be64(bytes: &[u8]) -> u64 {
+    u64::from_be_bytes(bytes[..8].try_into().unwrap())
+}
+
+// NH over a multiple of 32 bytes, message words are read little-endian (ENDIAN-SWAP)
+fn nh(key: &[u8], message: &[u8]) -> u64 {
+    let m = |i: usize| u32::from_le_bytes(message[4*i..4*i+4].try_into().unwrap());
+    let k = |i: usize| be32(&key[4*i..]);
+
+    let mut y: u64 = 0;
+    for i in (0..message.len() / 4).step_by(8) {
+        for j in 0..4 {
+            let a = m(i+j).wrapping_add(k(i+j)) as u64;
+            let b = m(i+j+4).wrapping_add(k(i+j+4)) as u64;
+            y = y.wrapping_add(a.wrapping_mul(b));
+        }
+    }
+    y
+}
+
+// NH of every 1024-byte chunk plus its length in bits
+fn l1_hash(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut result = Vec::new();
+    let chunks: Vec<&[u8]> = if message.is_empty() { vec![&[]] } else { message.chunks(L1_KEY_SIZE).collect() };
+
+    for chunk in chunks {
+        // Zero-padded to a positive multiple of 32 bytes
+        let mut padded = chunk.to_vec();
+        padded.resize(chunk.len().next_multiple_of(32).max(32), 0);
+        let y = nh(key, &padded).wrapping_add(chunk.len() as u64 * 8);
+        result.extend(y.to_be_bytes());
+    }
+    result
+}
+
+// (a * b + c) mod p for 128-bit values, by shift-and-add
+fn mul_add_mod128(a: u128, b: u128, c: u128) -> u128 {
+    let add = |x: u128, y: u128| {
+        let (sum, overflow) = x.overflowing_add(y);
+        if overflow || sum >= P128 { sum.wrapping_sub(P128) } else { sum }
+    };
+
+    let mut result = 0;
+    for i in (0..128).rev() {
+        result = add(result, result);
+        if (b >> i) & 1 == 1 {
+            result = add(result, a);
+        }
+    }
+    add(result, c % P128)
+}
+
+// POLY over 64-bit words, modulo 2^64 - 59
+fn poly64(key: u64, words: &[u8]) -> u64 {
+    let offset = 0u64.wrapping_sub(P64);
+    let step = |y: u64, m: u64| ((key as u128 * y as u128 + m as u128) % P64 as u128) as u64;
+
+    words.chunks_exact(8).fold(1, |y, word| {
+        let m = be64(word);
+        // Words too close to p are split in a marker and the value minus offset
+        if m >= 0u64.wrapping_sub(1 << 32) {
+            step(step(y, P64 - 1), m - offset)
+        } else {
+            step(y, m)
+        }
+    })
+}
+
+// POLY over 128-bit words, modulo 2^128 - 159
+fn poly128(key: u128, words: &[u8]) -> u128 {
+    let offset = 0u128.wrapping_sub(P128);
+
+    words.chunks_exact(16).fold(1, |y, word| {
+        let m = u128::from_be_bytes(word.try_into().unwrap());
+        if m >= 0u128.wrapping_sub(1 << 96) {
+            mul_add_mod128(key, mul_add_mod128(key, y, P128 - 1), m - offset)
+        } else {
+            mul_add_mod128(key, y, m)
+        }
+    })
+}
+
+fn l2_hash(key: &[u8], message: &[u8]) -> [u8; BLOCK_SIZE] {
+    let k64 = be64(key) & MASK64;
+    let k128 = u128::from_be_bytes(key[8..24].try_into().unwrap()) & MASK128;
+
+    let y = if message.len() <= L2_POLY64_LIMIT {
+        poly64(k64, message) as u128
+    } else {
+        let (first, second) = message.split_at(L2_POLY64_LIMIT);
+        // The 64-bit result becomes the first word of the 128-bit polynomial
+        let mut words = (poly64(k64, first) as u128).to_be_bytes().to_vec();
+        words.extend_from_slice(second);
+        words.push(0x80);
+        words.resize(words.len().next_multiple_of(16), 0);
+        poly128(k128, &words)
+    };
+
+    y.to_be_bytes()
+}
+
+fn l3_hash(key1: &[u8], key2: &[u8], message: &[u8; BLOCK_SIZE]) -> [u8; ITERATION_SIZE] {
+    let mut y: u64 = 0;
+    for i in 0..8 {
+        let m = u16::from_be_bytes([message[2*i], message[2*i+1]]) as u64;
+        let k = be64(&key1[8*i..]) % P36;
+        y = (y + m * k) % P36;
+    }
+
+    ((y as u32) ^ be32(key2)).to_be_bytes()
+}
+
+// UMAC (RFC 4418) with AES-128: UHASH of the message xored with a pad derived from the nonce
+#[allow(clippy::upper_case_acronyms)]
+pub struct UMAC {
+    tag_size: usize,
+    // AES under K' = KDF(K, 0), used by the PDF
+    pad_cipher: Box<AES128>,
+    l1_key: Vec<u8>,
+    l2_key: Vec<u8>,
+    l3_key1: Vec<u8>,
+    l3_key2: Vec<u8>,
+}
+
+impl UMAC {
+    pub fn new(key: &[u8], tag_size: usize) -> Result<UMAC, AESError> {
+        if !TAG_SIZES.contains(&tag_size) {
+            return Err(AESError::WrongTagSize(tag_size, BLOCK_SIZE));
+        }
+        let cipher = AES128::new(key, None)?;
+        let iterations = tag_size / ITERATION_SIZE;
+
+        Ok(UMAC {
+            tag_size,
+            pad_cipher: AES128::new(&kdf(&cipher, 0, BLOCK_SIZE), None)?,
+            l1_key: kdf(&cipher, 1, L1_KEY_SIZE + (iterations - 1) * L1_KEY_SHIFT),
+            l2_key: kdf(&cipher, 2, iterations * L2_KEY_SIZE),
+            l3_key1: kdf(&cipher, 3, iterations * L3_KEY1_SIZE),
+            l3_key2: kdf(&cipher, 4, iterations * L3_KEY2_SIZE),
+        })
+    }
+
+    // 32 bits per iteration, each with its own keys
+    fn uhash(&self, message: &[u8]) -> Vec<u8> {
+        let mut result = Vec::with_capacity(self.tag_size);
+        for i in 0..self.tag_size / ITERATION_SIZE {
+            let l1_key = &self.l1_key[i*L1_KEY_SHIFT..i*L1_KEY_SHIFT + L1_KEY_SIZE];
+            let a = l1_hash(l1_key, message);
+
+            // Short messages skip L2-HASH
+            let b = if message.len() <= L1_KEY_SIZE {
+                let mut b = [0; BLOCK_SIZE];
+                b[8..].copy_from_slice(&a);
+                b
+            } else {
+                l2_hash(&self.l2_key[i*L2_KEY_SIZE..(i+1)*L2_KEY_SIZE], &a)
+            };
+
+            let key1 = &self.l3_key1[i*L3_KEY1_SIZE..(i+1)*L3_KEY1_SIZE];
+            let key2 = &self.l3_key2[i*L3_KEY2_SIZE..(i+1)*L3_KEY2_SIZE];
+            result.extend(l3_hash(key1, key2, &b));
+        }
+        result
+    }
+
+    // PDF: AES_K'(nonce), 32 and 64-bit tags use the low bits of the nonce to pick a slice
+    fn pad(&self, nonce: &[u8]) -> Result<Vec<u8>, AESError> {
+        if nonce.is_empty() || nonce.len() > BLOCK_SIZE {
+            return Err(AESError::WrongNonceSize(nonce.len(), BLOCK_SIZE));
+        }
+        let mut block = [0; BLOCK_SIZE];
+        block[..nonce.len()].copy_from_slice(nonce);
+
+        let index = if self.tag_size <= 8 {
+            let index = (nonce[nonce.len()-1] as usize) % (BLOCK_SIZE / self.tag_size);
+            block[nonce.len()-1] ^= index as u8;
+            index
+        } else {
+            0
+        };
+
+        let t = self.pad_cipher.encrypt_block(&block);
+        Ok(t[index*self.tag_size..(index+1)*self.tag_size].to_vec())
+    }
+
+    // The nonce (1 to 16 bytes) must not repeat under the same key
+    pub fn tag(&self, nonce: &[u8], message: &[u8]) -> Result<Vec<u8>, AESError> {
+        let pad = self.pad(nonce)?;
+        Ok(self.uhash(message).iter().zip(pad).map(|(h, p)| h ^ p).collect())
+    }
+
+    pub fn verify(&self, nonce: &[u8], message: &[u8], tag: &[u8]) -> Result<(), AESError> {
+        if tag.len() != self.tag_size {
+            return Err(AESError::WrongTagSize(tag.len(), self.tag_size));
+        }
+        if !constant_time_eq(&self.tag(nonce, message)?, tag) {
+            return Err(AESError::AuthenticationFailed);
+        }
+
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::{utils::decode, AESError};
+    use super::{mul_add_mod128, UMAC};
+
+    const KEY: &[u8] = b"abcdefghijklmnop";
+    const NONCE: &[u8] = b"bcdefghi";
+
+    fn message(pattern: &[u8], count: usize) -> Vec<u8> {
+        pattern.repeat(count)
+    }
+
+    // RFC 4418, Appendix: (message, UMAC-32, UMAC-64, UMAC-96)
+    #[test]
+    fn test_umac_vectors() {
+        let vectors = [
+            (message(b"", 0), "113145fb", "6e155fad26900be1", "32fedb100c79ad58f07ff764"),
+            (message(b"a", 3), "3b91d102", "44b5cb542f220104", "185e4fe905cba7bd85e4c2dc"),
+            (message(b"a", 1 << 10), "599b350b", "26bf2f5d60118bd9", "7a54abe04af82d60fb298c3c"),
+            (message(b"a", 1 << 15), "58dcf532", "27f8ef643b0d118d", "7b136bd911e4b734286ef2be"),
+            (message(b"abc", 1), "abf3a3a0", "d4d7b9f6bd4fbfcf", "883c3d4b97a61976ffcf2323"),
+            (message(b"abc", 500), "abeb3c8b", "d4cf26ddefd5c01a", "8824a260c53c66a36c9260a6"),
+        ];
+
+        let umacs = [4, 8, 12].map(|size| UMAC::new(KEY, size).unwrap());
+        for (message, tag32, tag64, tag96) in vectors {
+            for (umac, expected) in umacs.iter().zip([tag32, tag64, tag96]) {
+                assert_eq!(umac.tag(NONCE, &message).unwrap(), decode(expected).unwrap());
+            }
+        }
+    }
+
+    // Messages above 1024 bytes go through L2-HASH
+    #[test]
+    fn test_umac_long_message() {
+        let umac = UMAC::new(KEY, 4).unwrap();
+        assert_eq!(umac.tag(NONCE, &message(b"a", 1 << 20)).unwrap(), decode("db6364d1").unwrap());
+    }
+
+    #[test]
+    fn test_mul_add_mod128() {
+        let p = 0u128.wrapping_sub(159);
+        assert_eq!(mul_add_mod128(0x0123456789abcdef0123456789abcdef, 0xfedcba9876543210fedcba9876543210, 5),
+                   0xf9a9f18c35a9a336ca7be6c6d7d57a01);
+        assert_eq!(mul_add_mod128(p - 1, p - 1, p - 1), 0);
+        // 2^128 = 159 (mod p)
+        assert_eq!(mul_add_mod128(1 << 127, 2, 0), 159);
+    }
+
+    #[test]
+    fn test_umac_128() {
+        // The first three iterations and the pad are the ones of UMAC-96
+        let umac96 = UMAC::new(KEY, 12).unwrap();
+        let umac128 = UMAC::new(KEY, 16).unwrap();
+        for message in [message(b"abc", 1), message(b"a", 5000)] {
+            let tag = umac128.tag(NONCE, &message).unwrap();
+            assert_eq!(tag.len(), 16);
+            assert_eq!(tag[..12], umac96.tag(NONCE, &message).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_umac_verify() {
+        let umac = UMAC::new(KEY, 8).unwrap();
+        let tag = decode("d4d7b9f6bd4fbfcf").unwrap();
+        assert!(umac.verify(NONCE, b"abc", &tag).is_ok());
+        assert!(matches!(umac.verify(NONCE, b"abd", &tag), Err(AESError::AuthenticationFailed)));
+        assert!(matches!(umac.verify(b"bcdefghj", b"abc", &tag), Err(AESError::AuthenticationFailed)));
+        assert!(matches!(umac.verify(NONCE, b"abc", &tag[..4]), Err(AESError::WrongTagSize(4, 8))));
+
+        assert!(matches!(UMAC::new(KEY, 10), Err(AESError::WrongTagSize(10, 16))));
+        assert!(matches!(umac.tag(&[0; 17], b"abc"), Err(AESError::WrongNonceSize(17, 16))));
+    }
+}
@@ -3,9 +3,11 @@ use std::{fs::File, vec};
 use std::fmt::Write;
 use std::fs::OpenOptions;
 
-use crate::{AESError, BLOCK_SIZE, BYTES_PER_ROW};
+use crate::{AESError, AES, BLOCK_SIZE, BYTES_PER_ROW};
 
 const IRREDUCIBLE_POLY: u8 = 0x1B;
+// Low byte of x^128 + x^7 + x^2 + x + 1, the GF(2^128) polynomial used by block cipher modes
+const GF128_REDUCTION: u8 = 0x87;
 pub const S_BOX: [u8;256] = [99, 124, 119, 123, 242, 107, 111, 197, 48, 1, 103, 43, 254, 215, 171, 118, 
                         202, 130, 201, 125, 250, 89, 71, 240, 173, 212, 162, 175, 156, 164, 114, 192, 
                         183, 253, 147, 38, 54, 63, 247, 204, 52, 165, 229, 241, 113, 216, 49, 21, 4, 
@@ -51,7 +53,7 @@ pub(crate) fn read_from_file(filename: &str) -> Result<Vec<u8>, Error> {
 }
 
 // Write data (crypted) in the given file
-pub(crate) fn write_to_file(filename: &str, data: &Vec<u8>) -> Result<(),Error> {
+pub(crate) fn write_to_file(filename: &str, data: &[u8]) -> Result<(),Error> {
     let mut f = OpenOptions::new()
             .truncate(true)
             .write(true)
@@ -91,23 +93,23 @@ pub fn add_iv(block: &[u8;BLOCK_SIZE], iv: &[u8;BLOCK_SIZE]) -> [u8;BLOCK_SIZE]
     result
 }
 
-pub fn padding(data: &Vec<u8>) -> Vec<u8> {
+pub fn padding(data: &[u8]) -> Vec<u8> {
     let len = data.len();
     let padding_len = BLOCK_SIZE - (len % BLOCK_SIZE);
     // Padding length is between 0 and 16, so u8 is enough
     let mut padding = vec![padding_len as u8; padding_len];
-    let mut result = data.clone();
+    let mut result = data.to_vec();
     result.append(&mut padding);
 
     result
 }
 
-pub fn unpadding(data: &Vec<u8>) -> Result<Vec<u8>, AESError> {
+pub fn unpadding(data: &[u8]) -> Result<Vec<u8>, AESError> {
     let padding = data[data.len()-1]; // Take last byte to understand the padding length
     if padding as usize > BLOCK_SIZE {
         return Err(AESError::WrongPaddingValue(BLOCK_SIZE as u8, padding));
     }
-    let mut result = data.clone();
+    let mut result = data.to_vec();
     for i in 0..(padding as usize) {
         let tmp = result.pop().ok_or(AESError::WrongPaddingLength(i+1, padding as usize))?;
         if tmp != padding {
@@ -118,8 +120,8 @@ pub fn unpadding(data: &Vec<u8>) -> Result<Vec<u8>, AESError> {
     Ok(result)
 }
 
-pub fn split_in_blocks(data: &Vec<u8>) -> Result<Vec<[u8;BLOCK_SIZE]>, AESError> {
-    if data.len() % BLOCK_SIZE != 0 {
+pub fn split_in_blocks(data: &[u8]) -> Result<Vec<[u8;BLOCK_SIZE]>, AESError> {
+    if !data.len().is_multiple_of(BLOCK_SIZE) {
         dbg!(format!("String must be clearly divisible in blocks of {} size. 
                     Consider to use first padding method.", BLOCK_SIZE));
         return Err(AESError::DataNotDivisibleInBlocks(data.len(), BLOCK_SIZE));
@@ -132,8 +134,8 @@ pub fn split_in_blocks(data: &Vec<u8>) -> Result<Vec<[u8;BLOCK_SIZE]>, AESError>
     Ok(chunks)
 }
 
-pub fn unite_blocks(data: &Vec<[u8;16]>) -> Vec<u8> {
-    data.clone().into_iter().flatten().collect()
+pub fn unite_blocks(data: &[[u8;16]]) -> Vec<u8> {
+    data.iter().flatten().copied().collect()
 }
 
 fn rotl8(x: u8, mut shift: u32) -> u8 {
@@ -213,7 +215,110 @@ pub(crate) fn gf_multiplication(mut a: u8, mut b: u8) -> u8 {
     result
 }
 
+// Multiply a 128-bit block by x in GF(2^128) (big-endian bit order)
+pub(crate) fn gf128_double(block: &[u8; BLOCK_SIZE]) -> [u8; BLOCK_SIZE] {
+    let mut result = [0; BLOCK_SIZE];
+    let carry = block[0] >> 7;
+
+    for i in 0..BLOCK_SIZE-1 {
+        result[i] = (block[i] << 1) | (block[i+1] >> 7);
+    }
+    // If the top bit was set, reduce by the field polynomial
+    result[BLOCK_SIZE-1] = (block[BLOCK_SIZE-1] << 1) ^ (carry * GF128_REDUCTION);
+
+    result
+}
+
+// Multiplication in GF(2^128) (big-endian bit order, same field as gf128_double)
+pub(crate) fn gf128_multiplication(a: &[u8; BLOCK_SIZE], b: &[u8; BLOCK_SIZE]) -> [u8; BLOCK_SIZE] {
+    let mut result = [0; BLOCK_SIZE];
+
+    // Horner's rule starting from the most significant bit of 'b'
+    for byte in b {
+        for bit in (0..8).rev() {
+            result = gf128_double(&result);
+            if (byte >> bit) & 1 == 1 {
+                result = add_iv(&result, a);
+            }
+        }
+    }
+
+    result
+}
+
+// Divide a 128-bit block by x in GF(2^128) (big-endian bit order)
+pub(crate) fn gf128_half(block: &[u8; BLOCK_SIZE]) -> [u8; BLOCK_SIZE] {
+    let mut result = [0; BLOCK_SIZE];
+    let carry = block[BLOCK_SIZE-1] & 1;
+
+    result[0] = block[0] >> 1;
+    for i in 1..BLOCK_SIZE {
+        result[i] = (block[i] >> 1) | (block[i-1] << 7);
+    }
+    // x^-1 = x^127 + x^6 + x + 1
+    result[0] ^= carry << 7;
+    result[BLOCK_SIZE-1] ^= carry * (GF128_REDUCTION >> 1);
+
+    result
+}
+
+// Multiply a 128-bit block by x in GF(2^128) with little-endian byte order (as in XTS)
+pub(crate) fn gf128_double_le(block: &[u8; BLOCK_SIZE]) -> [u8; BLOCK_SIZE] {
+    let mut result = [0; BLOCK_SIZE];
+    let carry = block[BLOCK_SIZE-1] >> 7;
+
+    for i in (1..BLOCK_SIZE).rev() {
+        result[i] = (block[i] << 1) | (block[i-1] >> 7);
+    }
+    result[0] = (block[0] << 1) ^ (carry * GF128_REDUCTION);
+
+    result
+}
+
+// Add one to the block seen as a big-endian 128-bit integer (wraps around)
+pub(crate) fn increment_counter(counter: &mut [u8; BLOCK_SIZE]) {
+    for b in counter.iter_mut().rev() {
+        *b = b.wrapping_add(1);
+        if *b != 0 {
+            break;
+        }
+    }
+}
+
+// Xor data with the keystream E(counter), E(counter+1), ... (CTR mode)
+pub(crate) fn ctr_xor<A: AES>(cipher: &A, counter: &[u8; BLOCK_SIZE], data: &[u8]) -> Vec<u8> {
+    let mut counter = *counter;
+    let mut result = Vec::with_capacity(data.len());
+
+    for chunk in data.chunks(BLOCK_SIZE) {
+        let keystream = cipher.encrypt_block(&counter);
+        for (i, b) in chunk.iter().enumerate() {
+            result.push(b ^ keystream[i]);
+        }
+        increment_counter(&mut counter);
+    }
+
+    result
+}
+
+// Compare two byte strings without stopping at the first difference,
+// so the time taken does not reveal where a tag is wrong
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    let mut diff = 0;
+    for i in 0..a.len() {
+        diff |= a[i] ^ b[i];
+    }
+
+    diff == 0
+}
+
 // Swap columns with rows
+// (the loops swap matrix[i][j] with matrix[j][i], so they need both indices)
+#[allow(clippy::needless_range_loop)]
 pub(crate) fn transpose(matrix: &mut [[u8; BYTES_PER_ROW]; BYTES_PER_ROW]) {
     // Assume matrix is square
     for i in 0..matrix.len() {
@@ -238,7 +343,7 @@ pub(crate) fn array_to_matrix(arr: &[u8; 16]) -> [[u8; BYTES_PER_ROW]; BYTES_PER
 pub(crate) fn matrix_to_array(matrix: &[[u8; BYTES_PER_ROW]; BYTES_PER_ROW]) -> [u8; 16] {
     let mut result = [0; 16];
 
-    let mut tmp = matrix.clone();
+    let mut tmp = *matrix;
     transpose(&mut tmp);
     for (i, m) in tmp.into_iter().flatten().enumerate() {
         result[i] = m;
@@ -249,8 +354,10 @@ pub(crate) fn matrix_to_array(matrix: &[[u8; BYTES_PER_ROW]; BYTES_PER_ROW]) ->
 
 
 #[cfg(test)]
+// Tests from before the lints were enforced, kept as written
+#[allow(clippy::needless_range_loop)]
 mod tests {
-    use crate::{utils::{add_iv, decode, encode, gf_multiplication, matrix_to_array, rotl8, transpose, INVERSE_S_BOX}, BLOCK_SIZE};
+    use crate::{utils::{add_iv, constant_time_eq, decode, encode, gf128_double, gf128_double_le, gf128_half, gf128_multiplication, gf_multiplication, increment_counter, matrix_to_array, rotl8, transpose, INVERSE_S_BOX}, BLOCK_SIZE};
 
     use super::{array_to_matrix, compute_inverse_s_box, compute_s_box, padding, unite_blocks, unpadding, S_BOX};
 
@@ -388,4 +495,81 @@ mod tests {
         transpose(&mut mat);
         assert_eq!(expected, mat);
     }
+
+    #[test]
+    fn test_gf128_double() {
+        let mut block = [0; BLOCK_SIZE];
+        block[BLOCK_SIZE-1] = 0x01;
+        let mut expected = [0; BLOCK_SIZE];
+        expected[BLOCK_SIZE-1] = 0x02;
+        assert_eq!(gf128_double(&block), expected);
+
+        // Overflowing bit is reduced by the polynomial
+        let mut block = [0; BLOCK_SIZE];
+        block[0] = 0x80;
+        let mut expected = [0; BLOCK_SIZE];
+        expected[BLOCK_SIZE-1] = 0x87;
+        assert_eq!(gf128_double(&block), expected);
+    }
+
+    #[test]
+    fn test_gf128_multiplication() {
+        let a: [u8; BLOCK_SIZE] = [0x12, 0x34, 0x56, 0x78, 0x9a, 0xbc, 0xde, 0xf0,
+                                    0x0f, 0xed, 0xcb, 0xa9, 0x87, 0x65, 0x43, 0x21];
+        let b = [0xa5; BLOCK_SIZE];
+        let mut one = [0; BLOCK_SIZE];
+        one[BLOCK_SIZE-1] = 1;
+        let mut two = [0; BLOCK_SIZE];
+        two[BLOCK_SIZE-1] = 2;
+
+        assert_eq!(gf128_multiplication(&a, &one), a);
+        assert_eq!(gf128_multiplication(&a, &two), gf128_double(&a));
+        assert_eq!(gf128_multiplication(&a, &b), gf128_multiplication(&b, &a));
+        assert_eq!(gf128_multiplication(&a, &[0; BLOCK_SIZE]), [0; BLOCK_SIZE]);
+    }
+
+    #[test]
+    fn test_gf128_half() {
+        let block: [u8; BLOCK_SIZE] = [0x12, 0x34, 0x56, 0x78, 0x9a, 0xbc, 0xde, 0xf0,
+                                        0x0f, 0xed, 0xcb, 0xa9, 0x87, 0x65, 0x43, 0x21];
+        assert_eq!(gf128_half(&gf128_double(&block)), block);
+        assert_eq!(gf128_double(&gf128_half(&block)), block);
+    }
+
+    #[test]
+    fn test_gf128_double_le() {
+        let mut block = [0; BLOCK_SIZE];
+        block[0] = 0x81;
+        let mut expected = [0; BLOCK_SIZE];
+        expected[0] = 0x02;
+        expected[1] = 0x01;
+        assert_eq!(gf128_double_le(&block), expected);
+
+        // Overflowing bit is reduced by the polynomial
+        let mut block = [0; BLOCK_SIZE];
+        block[BLOCK_SIZE-1] = 0x80;
+        let mut expected = [0; BLOCK_SIZE];
+        expected[0] = 0x87;
+        assert_eq!(gf128_double_le(&block), expected);
+    }
+
+    #[test]
+    fn test_increment_counter() {
+        let mut counter = [0; BLOCK_SIZE];
+        counter[BLOCK_SIZE-1] = 0xff;
+        increment_counter(&mut counter);
+        assert_eq!(counter[BLOCK_SIZE-2..], [0x01, 0x00]);
+
+        // Wrap around
+        let mut counter = [0xff; BLOCK_SIZE];
+        increment_counter(&mut counter);
+        assert_eq!(counter, [0; BLOCK_SIZE]);
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(&[1, 2, 3], &[1, 2, 3]));
+        assert!(!constant_time_eq(&[1, 2, 3], &[1, 2, 4]));
+        assert!(!constant_time_eq(&[1, 2, 3], &[1, 2]));
+    }
 }
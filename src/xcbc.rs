@@ -0,0 +1,140 @@
+use crate::{aes128::AES128, utils::{add_iv, constant_time_eq}, AESError, AES, BLOCK_SIZE};
+
+// AES-XCBC-MAC-96 keeps only the first 96 bits
+const MAC_96_SIZE: usize = 12;
+const KEY_SIZE_BYTES: usize = 16;
+
+
+// AES-XCBC-MAC (RFC 3566): CBC-MAC under K1 with K2 or K3 xored into the last block
+#[allow(clippy::upper_case_acronyms)]
+pub struct XCBC {
+    k1: Box<AES128>,
+    k2: [u8; BLOCK_SIZE],
+    k3: [u8; BLOCK_SIZE],
+}
+
+impl XCBC {
+    // Derive K1 = E_K(0x01..), K2 = E_K(0x02..), K3 = E_K(0x03..)
+    pub fn new(key: &[u8]) -> Result<XCBC, AESError> {
+        let aes = AES128::new(key, None)?;
+        let k1 = aes.encrypt_block(&[0x01; BLOCK_SIZE]);
+
+        Ok(XCBC {
+            k1: AES128::new(&k1, None)?,
+            k2: aes.encrypt_block(&[0x02; BLOCK_SIZE]),
+            k3: aes.encrypt_block(&[0x03; BLOCK_SIZE]),
+        })
+    }
+
+    // Full 128-bit MAC
+    pub fn mac(&self, data: &[u8]) -> [u8; BLOCK_SIZE] {
+        let mut state = [0; BLOCK_SIZE];
+
+        let blocks = if data.is_empty() { 0 } else { (data.len() - 1) / BLOCK_SIZE };
+        for i in 0..blocks {
+            let block: [u8; BLOCK_SIZE] = data[i*BLOCK_SIZE..(i+1)*BLOCK_SIZE].try_into().unwrap();
+            state = self.k1.encrypt_block(&add_iv(&state, &block));
+        }
+
+        // Complete last block uses K2, a padded (or empty) one uses K3
+        let rest = &data[blocks*BLOCK_SIZE..];
+        let mut last = [0; BLOCK_SIZE];
+        last[..rest.len()].copy_from_slice(rest);
+        let last = if rest.len() == BLOCK_SIZE {
+            add_iv(&last, &self.k2)
+        } else {
+            last[rest.len()] = 0x80;
+            add_iv(&last, &self.k3)
+        };
+
+        self.k1.encrypt_block(&add_iv(&state, &last))
+    }
+
+    // AES-XCBC-MAC-96 as used by IPsec
+    pub fn mac_96(&self, data: &[u8]) -> [u8; MAC_96_SIZE] {
+        self.mac(data)[..MAC_96_SIZE].try_into().unwrap()
+    }
+
+    pub fn verify_96(&self, data: &[u8], tag: &[u8]) -> Result<(), AESError> {
+        if tag.len() != MAC_96_SIZE {
+            return Err(AESError::WrongTagSize(tag.len(), MAC_96_SIZE));
+        }
+        if !constant_time_eq(&self.mac_96(data), tag) {
+            return Err(AESError::AuthenticationFailed);
+        }
+
+        Ok(())
+    }
+}
+
+// AES-XCBC-PRF-128 (RFC 4434), the key can have any length
+pub fn xcbc_prf_128(key: &[u8], data: &[u8]) -> [u8; BLOCK_SIZE] {
+    let key: [u8; KEY_SIZE_BYTES] = if key.len() <= KEY_SIZE_BYTES {
+        // Shorter keys are padded with zeros
+        let mut padded = [0; KEY_SIZE_BYTES];
+        padded[..key.len()].copy_from_slice(key);
+        padded
+    } else {
+        // Longer keys are first compressed with the all-zero key
+        XCBC::new(&[0; KEY_SIZE_BYTES]).unwrap().mac(key)
+    };
+
+    // The key has the right size at this point
+    XCBC::new(&key).unwrap().mac(data)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::{utils::decode, AESError};
+    use super::{xcbc_prf_128, XCBC};
+
+    const KEY: &str = "000102030405060708090a0b0c0d0e0f";
+
+    #[test]
+    fn test_xcbc_mac() {
+        // RFC 3566 test cases 1-7 (message length, MAC)
+        let vectors = [
+            (0, "75f0251d528ac01c4573dfd584d79f29"),
+            (3, "5b376580ae2f19afe7219ceef172756f"),
+            (16, "d2a246fa349b68a79998a4394ff7a263"),
+            (20, "47f51b4564966215b8985c63055ed308"),
+            (32, "f54f0ec8d2b9f3d36807734bd5283fd4"),
+            (34, "becbb3bccdb518a30677d5481fb6b4d8"),
+        ];
+        let xcbc = XCBC::new(&decode(KEY).unwrap()).unwrap();
+
+        for (len, expected) in vectors {
+            let data: Vec<u8> = (0..len).map(|i| i as u8).collect();
+            assert_eq!(xcbc.mac(&data).to_vec(), decode(expected).unwrap());
+        }
+        assert_eq!(xcbc.mac(&[0; 1000]).to_vec(), decode("f0dafee895db30253761103b5d84528f").unwrap());
+    }
+
+    #[test]
+    fn test_xcbc_mac_96() {
+        let xcbc = XCBC::new(&decode(KEY).unwrap()).unwrap();
+        let data = decode("000102").unwrap();
+        let tag = xcbc.mac_96(&data);
+
+        assert_eq!(tag.to_vec(), decode("5b376580ae2f19afe7219cee").unwrap());
+        assert!(xcbc.verify_96(&data, &tag).is_ok());
+        assert!(matches!(xcbc.verify_96(&data[..2], &tag), Err(AESError::AuthenticationFailed)));
+        assert!(matches!(xcbc.verify_96(&data, &[0; 16]), Err(AESError::WrongTagSize(16, 12))));
+    }
+
+    #[test]
+    fn test_xcbc_prf_128() {
+        // RFC 4434 test cases with keys of 16, 10 and 18 bytes
+        let data = decode("000102030405060708090a0b0c0d0e0f10111213").unwrap();
+        let vectors = [
+            (KEY, "47f51b4564966215b8985c63055ed308"),
+            ("00010203040506070809", "0fa087af7d866e7653434e602fdde835"),
+            ("000102030405060708090a0b0c0d0e0fedcb", "8cd3c93ae598a9803006ffb67c40e9e4"),
+        ];
+
+        for (key, expected) in vectors {
+            assert_eq!(xcbc_prf_128(&decode(key).unwrap(), &data).to_vec(), decode(expected).unwrap());
+        }
+    }
+}
@@ -0,0 +1,289 @@
+use std::io::{Error, ErrorKind};
+use crate::{utils::{add_iv, gf128_double_le, read_from_file, write_to_file}, AESError, AES, BLOCK_SIZE};
+
+
+// XTS-AES (IEEE 1619) for sector-oriented storage: every sector is encrypted
+// independently with a tweak derived from its number, no padding is needed
+#[allow(clippy::upper_case_acronyms)]
+pub struct XTS<A: AES> {
+    data_cipher: Box<A>,
+    tweak_cipher: Box<A>,
+}
+
+impl<A: AES> XTS<A> {
+    // The key is Key1 || Key2, first half encrypts data, second half encrypts the tweak
+    pub fn new(key: &[u8]) -> Result<XTS<A>, AESError> {
+        let (data_key, tweak_key) = key.split_at(key.len() / 2);
+
+        Ok(XTS {
+            data_cipher: A::new(data_key, None)?,
+            tweak_cipher: A::new(tweak_key, None)?,
+        })
+    }
+
+    // T = E_K2(i), with the sector number i encoded as 128-bit little-endian integer
+    fn initial_tweak(&self, sector_number: u128) -> [u8; BLOCK_SIZE] {
+        self.tweak_cipher.encrypt_block(&sector_number.to_le_bytes())
+    }
+
+    fn encrypt_with_tweak(&self, block: &[u8], tweak: &[u8; BLOCK_SIZE]) -> [u8; BLOCK_SIZE] {
+        let block: [u8; BLOCK_SIZE] = block.try_into().unwrap();
+        add_iv(&self.data_cipher.encrypt_block(&add_iv(&block, tweak)), tweak)
+    }
+
+    fn decrypt_with_tweak(&self, block: &[u8], tweak: &[u8; BLOCK_SIZE]) -> [u8; BLOCK_SIZE] {
+        let block: [u8; BLOCK_SIZE] = block.try_into().unwrap();
+        add_iv(&self.data_cipher.decrypt_block(&add_iv(&block, tweak)), tweak)
+    }
+
+    // Encrypt one sector in place, the length must be at least one block
+    pub fn encrypt_sector(&self, sector_number: u128, data: &mut [u8]) -> Result<(), AESError> {
+        if data.len() < BLOCK_SIZE {
+            return Err(AESError::DataTooShort(data.len(), BLOCK_SIZE));
+        }
+        let blocks = data.len() / BLOCK_SIZE;
+        let rest = data.len() % BLOCK_SIZE;
+
+        let mut tweak = self.initial_tweak(sector_number);
+        for j in 0..blocks {
+            let range = j*BLOCK_SIZE..(j+1)*BLOCK_SIZE;
+            let encrypted = self.encrypt_with_tweak(&data[range.clone()], &tweak);
+            data[range].copy_from_slice(&encrypted);
+            tweak = gf128_double_le(&tweak);
+        }
+
+        // Ciphertext stealing: the last partial block borrows the tail of the previous ciphertext
+        if rest != 0 {
+            let last = (blocks-1)*BLOCK_SIZE;
+            let tail = blocks*BLOCK_SIZE;
+            let stolen: [u8; BLOCK_SIZE] = data[last..tail].try_into().unwrap();
+
+            let mut block = stolen;
+            block[..rest].copy_from_slice(&data[tail..]);
+            data[tail..].copy_from_slice(&stolen[..rest]);
+            let encrypted = self.encrypt_with_tweak(&block, &tweak);
+            data[last..tail].copy_from_slice(&encrypted);
+        }
+
+        Ok(())
+    }
+
+    // Decrypt one sector in place, the length must be at least one block
+    pub fn decrypt_sector(&self, sector_number: u128, data: &mut [u8]) -> Result<(), AESError> {
+        if data.len() < BLOCK_SIZE {
+            return Err(AESError::DataTooShort(data.len(), BLOCK_SIZE));
+        }
+        let blocks = data.len() / BLOCK_SIZE;
+        let rest = data.len() % BLOCK_SIZE;
+
+        // With stealing, the last full block is handled together with the partial one
+        let full_blocks = if rest == 0 { blocks } else { blocks - 1 };
+        let mut tweak = self.initial_tweak(sector_number);
+        for j in 0..full_blocks {
+            let range = j*BLOCK_SIZE..(j+1)*BLOCK_SIZE;
+            let decrypted = self.decrypt_with_tweak(&data[range.clone()], &tweak);
+            data[range].copy_from_slice(&decrypted);
+            tweak = gf128_double_le(&tweak);
+        }
+
+        if rest != 0 {
+            let last = (blocks-1)*BLOCK_SIZE;
+            let tail = blocks*BLOCK_SIZE;
+            // Last full ciphertext block was encrypted with the following tweak
+            let stolen = self.decrypt_with_tweak(&data[last..tail], &gf128_double_le(&tweak));
+
+            let mut block = stolen;
+            block[..rest].copy_from_slice(&data[tail..]);
+            data[tail..].copy_from_slice(&stolen[..rest]);
+            let decrypted = self.decrypt_with_tweak(&block, &tweak);
+            data[last..tail].copy_from_slice(&decrypted);
+        }
+
+        Ok(())
+    }
+
+    // Every sector, including a shorter last one, must be at least one block, and the
+    // last sector number must fit in 128 bits. Checked up front so that nothing is
+    // modified when the layout is wrong
+    fn check_sectors(first_sector: u128, sector_size: usize, length: usize) -> Result<(), AESError> {
+        if sector_size < BLOCK_SIZE {
+            return Err(AESError::DataTooShort(sector_size, BLOCK_SIZE));
+        }
+        let tail = length % sector_size;
+        if tail != 0 && tail < BLOCK_SIZE {
+            return Err(AESError::DataTooShort(tail, BLOCK_SIZE));
+        }
+        let sectors = length.div_ceil(sector_size) as u128;
+        if sectors > 0 && first_sector.checked_add(sectors - 1).is_none() {
+            return Err(AESError::SectorNumberOverflow(first_sector));
+        }
+        Ok(())
+    }
+
+    // Encrypt consecutive sectors starting from the given sector number (last one may be shorter)
+    pub fn encrypt_sectors(&self, first_sector: u128, sector_size: usize, data: &mut [u8]) -> Result<(), AESError> {
+        Self::check_sectors(first_sector, sector_size, data.len())?;
+        for (i, sector) in data.chunks_mut(sector_size).enumerate() {
+            self.encrypt_sector(first_sector + i as u128, sector)?;
+        }
+        Ok(())
+    }
+
+    pub fn decrypt_sectors(&self, first_sector: u128, sector_size: usize, data: &mut [u8]) -> Result<(), AESError> {
+        Self::check_sectors(first_sector, sector_size, data.len())?;
+        for (i, sector) in data.chunks_mut(sector_size).enumerate() {
+            self.decrypt_sector(first_sector + i as u128, sector)?;
+        }
+        Ok(())
+    }
+
+    // Encrypt a disk image sector by sector, the output has the same size as the input
+    pub fn encrypt_file(&self, filename: &str, sector_size: usize) -> Result<(), Error> {
+        let mut data = read_from_file(filename)?;
+        self.encrypt_sectors(0, sector_size, &mut data)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, format!("{:?}", e)))?;
+
+        write_to_file(&("crypted_".to_string() + filename), &data)
+    }
+
+    pub fn decrypt_file(&self, filename: &str, sector_size: usize) -> Result<(), Error> {
+        let mut data = read_from_file(filename)?;
+        self.decrypt_sectors(0, sector_size, &mut data)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, format!("{:?}", e)))?;
+
+        write_to_file(&("decrypted_".to_string() + filename), &data)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::{aes128::AES128, utils::decode, AESError};
+    use super::XTS;
+
+    // IEEE 1619 vectors 1-3 (key1 || key2, sector number, plaintext, ciphertext)
+    const VECTORS: [(&str, u128, &str, &str); 3] = [
+        ("0000000000000000000000000000000000000000000000000000000000000000", 0,
+            "0000000000000000000000000000000000000000000000000000000000000000",
+            "917cf69ebd68b2ec9b9fe9a3eadda692cd43d2f59598ed858c02c2652fbf922e"),
+        ("1111111111111111111111111111111122222222222222222222222222222222", 0x3333333333,
+            "4444444444444444444444444444444444444444444444444444444444444444",
+            "c454185e6a16936e39334038acef838bfb186fff7480adc4289382ecd6d394f0"),
+        ("fffefdfcfbfaf9f8f7f6f5f4f3f2f1f022222222222222222222222222222222", 0x3333333333,
+            "4444444444444444444444444444444444444444444444444444444444444444",
+            "af85336b597afc1a900b2eb21ec949d292df4c047e0b21532186a5971a227a89"),
+    ];
+
+    // IEEE 1619 vectors 15-18, with ciphertext stealing
+    const STEALING_VECTORS: [(&str, &str); 4] = [
+        ("000102030405060708090a0b0c0d0e0f10", "6c1625db4671522d3d7599601de7ca09ed"),
+        ("000102030405060708090a0b0c0d0e0f1011", "d069444b7a7e0cab09e24447d24deb1fedbf"),
+        ("000102030405060708090a0b0c0d0e0f101112", "e5df1351c0544ba1350b3363cd8ef4beedbf9d"),
+        ("000102030405060708090a0b0c0d0e0f10111213", "9d84c813f719aa2c7be3f66171c7c5c2edbf9dac"),
+    ];
+
+    #[test]
+    fn test_xts_vectors() {
+        for (key, sector, plaintext, expected) in VECTORS {
+            let xts = XTS::<AES128>::new(&decode(key).unwrap()).unwrap();
+            let mut data = decode(plaintext).unwrap();
+
+            xts.encrypt_sector(sector, &mut data).unwrap();
+            assert_eq!(data, decode(expected).unwrap());
+            xts.decrypt_sector(sector, &mut data).unwrap();
+            assert_eq!(data, decode(plaintext).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_xts_512_bytes_sector() {
+        // IEEE 1619 vector 4
+        let key = decode("2718281828459045235360287471352631415926535897932384626433832795").unwrap();
+        let xts = XTS::<AES128>::new(&key).unwrap();
+        let plaintext: Vec<u8> = (0..512).map(|i| i as u8).collect();
+        let mut data = plaintext.clone();
+
+        xts.encrypt_sector(0, &mut data).unwrap();
+        let expected = decode("27a7479befa1d476489f308cd4cfa6e2a96e4bbe3208ff25287dd3819616e89cc78cf7f5e543445f8333d8fa7f560000\
+                                05279fa5d8b5e4ad40e736ddb4d35412328063fd2aab53e5ea1e0a9f332500a5df9487d07a5c92cc512c8866c7e860ce\
+                                93fdf166a24912b422976146ae20ce846bb7dc9ba94a767aaef20c0d61ad02655ea92dc4c4e41a8952c651d33174be51\
+                                a10c421110e6d81588ede82103a252d8a750e8768defffed9122810aaeb99f9172af82b604dc4b8e51bcb08235a6f434\
+                                1332e4ca60482a4ba1a03b3e65008fc5da76b70bf1690db4eae29c5f1badd03c5ccf2a55d705ddcd86d449511ceb7ec3\
+                                0bf12b1fa35b913f9f747a8afd1b130e94bff94effd01a91735ca1726acd0b197c4e5b03393697e126826fb6bbde8ecc\
+                                1e08298516e2c9ed03ff3c1b7860f6de76d4cecd94c8119855ef5297ca67e9f3e7ff72b1e99785ca0a7e7720c5b36dc6\
+                                d72cac9574c8cbbc2f801e23e56fd344b07f22154beba0f08ce8891e643ed995c94d9a69c9f1b5f499027a78572aeebd\
+                                74d20cc39881c213ee770b1010e4bea718846977ae119f7a023ab58cca0ad752afe656bb3c17256a9f6e9bf19fdd5a38\
+                                fc82bbe872c5539edb609ef4f79c203ebb140f2e583cb2ad15b4aa5b655016a8449277dbd477ef2c8d6c017db738b18d\
+                                eb4a427d1923ce3ff262735779a418f20a282df920147beabe421ee5319d0568").unwrap();
+        assert_eq!(data, expected);
+        xts.decrypt_sector(0, &mut data).unwrap();
+        assert_eq!(data, plaintext);
+    }
+
+    #[test]
+    fn test_xts_stealing_vectors() {
+        let key = decode("fffefdfcfbfaf9f8f7f6f5f4f3f2f1f0bfbebdbcbbbab9b8b7b6b5b4b3b2b1b0").unwrap();
+        let xts = XTS::<AES128>::new(&key).unwrap();
+        for (plaintext, expected) in STEALING_VECTORS {
+            let mut data = decode(plaintext).unwrap();
+
+            xts.encrypt_sector(0x123456789a, &mut data).unwrap();
+            assert_eq!(data, decode(expected).unwrap());
+            xts.decrypt_sector(0x123456789a, &mut data).unwrap();
+            assert_eq!(data, decode(plaintext).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_xts_encrypt_decrypt_lengths() {
+        let xts = XTS::<AES128>::new(&[0x42; 32]).unwrap();
+        for len in 16..=64 {
+            let plaintext: Vec<u8> = (0..len).map(|i| i as u8).collect();
+            let mut data = plaintext.clone();
+
+            xts.encrypt_sector(7, &mut data).unwrap();
+            assert_ne!(data, plaintext);
+            xts.decrypt_sector(7, &mut data).unwrap();
+            assert_eq!(data, plaintext);
+        }
+    }
+
+    #[test]
+    fn test_xts_sectors() {
+        let xts = XTS::<AES128>::new(&[0x42; 32]).unwrap();
+        let plaintext = vec![0xab; 512 * 3 + 100];
+        let mut data = plaintext.clone();
+
+        xts.encrypt_sectors(10, 512, &mut data).unwrap();
+        // Same content in different sectors gives different ciphertext
+        assert_ne!(data[..512], data[512..1024]);
+        xts.decrypt_sectors(10, 512, &mut data).unwrap();
+        assert_eq!(data, plaintext);
+    }
+
+    #[test]
+    fn test_xts_short_sector() {
+        let xts = XTS::<AES128>::new(&[0x42; 32]).unwrap();
+        let mut data = [0; 15];
+        assert!(matches!(xts.encrypt_sector(0, &mut data), Err(AESError::DataTooShort(15, 16))));
+    }
+
+    #[test]
+    fn test_xts_wrong_sector_layout() {
+        let xts = XTS::<AES128>::new(&[0x42; 32]).unwrap();
+        let mut data = vec![0xab; 512 * 2 + 10];
+
+        assert!(matches!(xts.encrypt_sectors(0, 0, &mut data), Err(AESError::DataTooShort(0, 16))));
+        assert!(matches!(xts.decrypt_sectors(0, 8, &mut data), Err(AESError::DataTooShort(8, 16))));
+        // The short tail is rejected before the first sectors are touched
+        assert!(matches!(xts.encrypt_sectors(0, 512, &mut data), Err(AESError::DataTooShort(10, 16))));
+        assert!(matches!(xts.decrypt_sectors(0, 512, &mut data), Err(AESError::DataTooShort(10, 16))));
+        assert_eq!(data, vec![0xab; 512 * 2 + 10]);
+
+        // Sector numbers must not go past u128::MAX
+        let mut data = vec![0xab; 512 * 2];
+        assert!(xts.encrypt_sectors(u128::MAX - 1, 512, &mut data).is_ok());
+        assert!(matches!(xts.encrypt_sectors(u128::MAX, 512, &mut data), Err(AESError::SectorNumberOverflow(u128::MAX))));
+        assert!(matches!(xts.decrypt_sectors(u128::MAX, 512, &mut data), Err(AESError::SectorNumberOverflow(u128::MAX))));
+    }
+}